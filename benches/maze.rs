@@ -0,0 +1,27 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use cartographer_rs::maps::{Architect, MapSelector};
+
+/// Builds square mazes of increasing size. Time per element should stay roughly flat as the
+/// maze grows if generation scales linearly with the number of cells.
+fn maze_scaling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("maze_scaling");
+    group.sample_size(10);
+
+    for size in [50, 100, 200, 400] {
+        let cells = ((size / 2 - 2) * (size / 2 - 2)) as u64;
+        group.throughput(Throughput::Elements(cells));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+            b.iter(|| {
+                let mut m = MapSelector::maze_map(size, size);
+                m.build();
+                m
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, maze_scaling);
+criterion_main!(benches);
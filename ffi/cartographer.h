@@ -0,0 +1,206 @@
+#ifndef CARTOGRAPHER_H
+#define CARTOGRAPHER_H
+
+/* Generated with cbindgen:0.29.4 */
+
+/* Generated by cbindgen from src/ffi.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Width and height big enough for every generator; `cartographer_min_size` gives each
+// generator's own minimum
+#define CARTOGRAPHER_MIN_SIZE 24
+
+enum CartographerGrid
+#if defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+  : uint8_t
+#endif // defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+ {
+  CARTOGRAPHER_GRID_SQUARE = 0,
+  CARTOGRAPHER_GRID_HEX = 1,
+};
+#ifndef __cplusplus
+#if __STDC_VERSION__ >= 202311L
+typedef enum CartographerGrid CartographerGrid;
+#else
+typedef uint8_t CartographerGrid;
+#endif // __STDC_VERSION__ >= 202311L
+#endif // __cplusplus
+
+enum CartographerTile
+#if defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+  : uint8_t
+#endif // defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+ {
+  CARTOGRAPHER_TILE_WALL = 0,
+  CARTOGRAPHER_TILE_FLOOR = 1,
+  CARTOGRAPHER_TILE_EXIT = 2,
+  CARTOGRAPHER_TILE_VOID = 3,
+};
+#ifndef __cplusplus
+#if __STDC_VERSION__ >= 202311L
+typedef enum CartographerTile CartographerTile;
+#else
+typedef uint8_t CartographerTile;
+#endif // __STDC_VERSION__ >= 202311L
+#endif // __cplusplus
+
+// Values for `CartographerParams::symmetry`
+enum CartographerSymmetry
+#if defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+  : uint8_t
+#endif // defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+ {
+  CARTOGRAPHER_SYMMETRY_NONE = 0,
+  CARTOGRAPHER_SYMMETRY_HORIZONTAL = 1,
+  CARTOGRAPHER_SYMMETRY_VERTICAL = 2,
+  CARTOGRAPHER_SYMMETRY_BOTH = 3,
+  CARTOGRAPHER_SYMMETRY_ROTATIONAL2 = 4,
+  CARTOGRAPHER_SYMMETRY_ROTATIONAL4 = 5,
+  // `radial_folds` copies spaced evenly around the centre
+  CARTOGRAPHER_SYMMETRY_RADIAL = 6,
+};
+#ifndef __cplusplus
+#if __STDC_VERSION__ >= 202311L
+typedef enum CartographerSymmetry CartographerSymmetry;
+#else
+typedef uint8_t CartographerSymmetry;
+#endif // __STDC_VERSION__ >= 202311L
+#endif // __cplusplus
+
+// A generator and the map it builds
+typedef struct CartographerMap CartographerMap;
+
+// Settings shared by the drunkard's walk and DLA generators
+typedef struct CartographerParams {
+  // Fraction of the map to turn into floor, above 0 and at most 1
+  float floor_percent;
+  // Size of the digging brush, at least 1. Drunkard presets with several kinds of digger
+  // keep their own brushes.
+  int32_t brush_size;
+  // One of the `CartographerSymmetry` values
+  uint8_t symmetry;
+  // Copies around the centre for `CARTOGRAPHER_SYMMETRY_RADIAL`, otherwise ignored
+  uint32_t radial_folds;
+} CartographerParams;
+
+typedef struct CartographerPosition {
+  int32_t x;
+  int32_t y;
+} CartographerPosition;
+
+// A room as the corners `(x1, y1)` to `(x2, y2)`
+typedef struct CartographerRoom {
+  int32_t x1;
+  int32_t y1;
+  int32_t x2;
+  int32_t y2;
+} CartographerRoom;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Number of generators which `cartographer_new` accepts
+size_t cartographer_generator_count(void);
+
+// Name of the generator at `index`, or NULL if it's out of range. The string is owned by the
+// library and lives for as long as the library stays loaded.
+const char *cartographer_generator_name(size_t index);
+
+// Smallest width and height the named generator builds a proper map at, or 0 if the name
+// is unknown
+//
+// # Safety
+// `generator` must be NULL or a valid NUL terminated string.
+int32_t cartographer_min_size(const char *generator);
+
+// Creates the named generator for a map of the given size. Returns NULL if the name is
+// unknown or either dimension is below the generator's `cartographer_min_size`.
+//
+// # Safety
+// `generator` must be NULL or a valid NUL terminated string.
+struct CartographerMap *cartographer_new(const char *generator, int32_t width, int32_t height);
+
+// Writes the named generator's usual settings to `out`. Returns false if the name is unknown
+// or the generator doesn't take `CartographerParams`.
+//
+// # Safety
+// `generator` must be NULL or a valid NUL terminated string, and `out` must be NULL or point
+// to a writable `CartographerParams`.
+bool cartographer_params(const char *generator, struct CartographerParams *out);
+
+// Creates the named generator like `cartographer_new`, with its settings replaced by
+// `params`. Start from `cartographer_params` to change only some of them. Returns NULL
+// where `cartographer_new` would, if the generator doesn't take `CartographerParams`, or if
+// any of them is out of range.
+//
+// # Safety
+// `generator` must be NULL or a valid NUL terminated string, and `params` must be NULL or
+// point to a `CartographerParams`.
+struct CartographerMap *cartographer_new_with_params(const char *generator,
+                                                     int32_t width,
+                                                     int32_t height,
+                                                     const struct CartographerParams *params);
+
+// Builds the map from `seed`, replacing anything built before. Returns false if the
+// generator failed, in which case the map contents shouldn't be relied on.
+//
+// # Safety
+// `map` must be NULL or a pointer returned by `cartographer_new` which hasn't been freed.
+bool cartographer_build(struct CartographerMap *map, uint64_t seed);
+
+// # Safety
+// `map` must be NULL or a pointer returned by `cartographer_new` which hasn't been freed.
+int32_t cartographer_width(const struct CartographerMap *map);
+
+// # Safety
+// `map` must be NULL or a pointer returned by `cartographer_new` which hasn't been freed.
+int32_t cartographer_height(const struct CartographerMap *map);
+
+// # Safety
+// `map` must be NULL or a pointer returned by `cartographer_new` which hasn't been freed.
+CartographerGrid cartographer_grid(const struct CartographerMap *map);
+
+// Copies up to `len` tiles, row by row, into `out` and returns the total number of tiles in
+// the map. Pass a NULL `out` to just ask for the count.
+//
+// # Safety
+// `map` must be NULL or a live pointer from `cartographer_new`, and `out` must be NULL or
+// point to at least `len` writable tiles.
+size_t cartographer_tiles(const struct CartographerMap *map, CartographerTile *out, size_t len);
+
+// # Safety
+// `map` must be NULL or a pointer returned by `cartographer_new` which hasn't been freed.
+struct CartographerPosition cartographer_start(const struct CartographerMap *map);
+
+// Number of rooms in the map. Only the room based generators produce any.
+//
+// # Safety
+// `map` must be NULL or a pointer returned by `cartographer_new` which hasn't been freed.
+size_t cartographer_room_count(const struct CartographerMap *map);
+
+// Writes the room at `index` to `out`, returning false if there's no such room.
+//
+// # Safety
+// `map` must be NULL or a live pointer from `cartographer_new`, and `out` must be NULL or
+// point to a writable `CartographerRoom`.
+bool cartographer_room(const struct CartographerMap *map,
+                       size_t index,
+                       struct CartographerRoom *out);
+
+// Releases a generator. Passing NULL does nothing.
+//
+// # Safety
+// `map` must be NULL or a pointer returned by `cartographer_new` which hasn't been freed.
+void cartographer_free(struct CartographerMap *map);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CARTOGRAPHER_H */
@@ -0,0 +1,146 @@
+/* Builds every generator through the C interface and checks the results hang together.
+ * Run with `make -C ffi test` from the crate root. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "cartographer.h"
+
+static int failures = 0;
+
+#define CHECK(cond, ...) do { \
+    if (!(cond)) { \
+        fprintf(stderr, "FAIL %s:%d: ", __FILE__, __LINE__); \
+        fprintf(stderr, __VA_ARGS__); \
+        fprintf(stderr, "\n"); \
+        failures++; \
+    } \
+} while (0)
+
+static void check_generator(const char *name) {
+    const int32_t width = 60, height = 40;
+
+    int32_t min_size = cartographer_min_size(name);
+    CHECK(min_size > 0 && min_size <= CARTOGRAPHER_MIN_SIZE, "%s: min size %d", name, min_size);
+    CHECK(cartographer_new(name, min_size - 1, height) == NULL, "%s: map below min size accepted", name);
+
+    CartographerMap *map = cartographer_new(name, width, height);
+    CHECK(map != NULL, "%s: could not create generator", name);
+    if (map == NULL) return;
+
+    CHECK(cartographer_build(map, 42), "%s: build failed", name);
+    CHECK(cartographer_width(map) == width, "%s: width %d", name, cartographer_width(map));
+    CHECK(cartographer_height(map) == height, "%s: height %d", name, cartographer_height(map));
+
+    size_t count = cartographer_tiles(map, NULL, 0);
+    CHECK(count == (size_t)(width * height), "%s: %zu tiles", name, count);
+
+    CartographerTile tiles[60 * 40];
+    memset(tiles, 0xff, sizeof(tiles));
+    cartographer_tiles(map, tiles, count);
+
+    size_t floors = 0, exits = 0;
+    for (size_t i = 0; i < count; i++) {
+        CHECK(tiles[i] <= CARTOGRAPHER_TILE_VOID, "%s: bad tile %d at %zu", name, tiles[i], i);
+        if (tiles[i] == CARTOGRAPHER_TILE_FLOOR) floors++;
+        if (tiles[i] == CARTOGRAPHER_TILE_EXIT) exits++;
+    }
+    CHECK(floors > 0, "%s: no floor", name);
+    CHECK(exits == 1, "%s: %zu exits", name, exits);
+
+    CartographerPosition start = cartographer_start(map);
+    CHECK(start.x > 0 && start.x < width && start.y > 0 && start.y < height,
+          "%s: start (%d, %d) out of bounds", name, start.x, start.y);
+    CHECK(tiles[start.y * width + start.x] != CARTOGRAPHER_TILE_WALL, "%s: start is a wall", name);
+
+    for (size_t i = 0; i < cartographer_room_count(map); i++) {
+        CartographerRoom room;
+        CHECK(cartographer_room(map, i, &room), "%s: missing room %zu", name, i);
+        CHECK(room.x1 <= room.x2 && room.y1 <= room.y2, "%s: room %zu is inside out", name, i);
+    }
+    CartographerRoom unused;
+    CHECK(!cartographer_room(map, cartographer_room_count(map), &unused), "%s: room past the end", name);
+
+    /* The same seed always gives the same map */
+    CartographerMap *again = cartographer_new(name, width, height);
+    CartographerTile other[60 * 40];
+    cartographer_build(again, 42);
+    cartographer_tiles(again, other, count);
+    CHECK(memcmp(tiles, other, count) == 0, "%s: seeded build differs", name);
+    cartographer_free(again);
+
+    /* A generator's own params build the same map as plain creation */
+    CartographerParams params;
+    if (cartographer_params(name, &params)) {
+        CartographerMap *same = cartographer_new_with_params(name, width, height, &params);
+        CHECK(same != NULL && cartographer_build(same, 42), "%s: build with its own params failed", name);
+        cartographer_tiles(same, other, count);
+        CHECK(memcmp(tiles, other, count) == 0, "%s: own params build a different map", name);
+        cartographer_free(same);
+    }
+
+    /* Rebuilding a handle replaces the old map, so it matches a fresh build */
+    cartographer_build(map, 7);
+    CHECK(cartographer_build(map, 42), "%s: rebuild failed", name);
+    cartographer_tiles(map, other, count);
+    CHECK(memcmp(tiles, other, count) == 0, "%s: rebuild differs", name);
+
+    cartographer_free(map);
+}
+
+int main(void) {
+    CHECK(cartographer_new("no_such_generator", 60, 40) == NULL, "unknown generator accepted");
+    CHECK(cartographer_min_size("no_such_generator") == 0, "unknown generator has a min size");
+    CHECK(cartographer_min_size(NULL) == 0, "NULL name has a min size");
+    CHECK(cartographer_new(NULL, 60, 40) == NULL, "NULL name accepted");
+    CHECK(cartographer_generator_name(cartographer_generator_count()) == NULL, "name past the end");
+    cartographer_free(NULL);
+
+    CartographerMap *basic = cartographer_new("basic_map", 60, 40);
+    cartographer_build(basic, 7);
+    CHECK(cartographer_room_count(basic) > 0, "basic_map has no rooms");
+    CHECK(cartographer_grid(basic) == CARTOGRAPHER_GRID_SQUARE, "basic_map is not square");
+    cartographer_free(basic);
+
+    /* Drunkard and DLA generators take their settings from the caller */
+    CartographerParams params;
+    CHECK(!cartographer_params("basic_map", &params), "basic_map has params");
+    CHECK(cartographer_params("drunkard_walk_map_open_area", &params), "drunkard has no params");
+    CHECK(params.floor_percent == 0.5f && params.brush_size == 1 && params.symmetry == CARTOGRAPHER_SYMMETRY_NONE,
+          "drunkard params differ from the preset");
+    CHECK(cartographer_new_with_params("basic_map", 60, 40, &params) == NULL, "basic_map accepted params");
+
+    params.symmetry = CARTOGRAPHER_SYMMETRY_HORIZONTAL;
+    CartographerMap *mirrored = cartographer_new_with_params("drunkard_walk_map_open_area", 60, 40, &params);
+    CHECK(mirrored != NULL && cartographer_build(mirrored, 7), "drunkard with params failed");
+    CartographerTile mirror[60 * 40];
+    cartographer_tiles(mirrored, mirror, 60 * 40);
+    for (int y = 0; y < 40; y++) {
+        for (int x = 1; x < 59; x++) {
+            /* The exit only appears on one side */
+            int walkable = mirror[y * 60 + x] != CARTOGRAPHER_TILE_WALL;
+            int opposite = mirror[y * 60 + 59 - x] != CARTOGRAPHER_TILE_WALL;
+            CHECK(walkable == opposite, "mirrored drunkard differs at (%d, %d)", x, y);
+        }
+    }
+    cartographer_free(mirrored);
+
+    params.symmetry = 42;
+    CHECK(cartographer_new_with_params("drunkard_walk_map_open_area", 60, 40, &params) == NULL, "bad symmetry accepted");
+    params.symmetry = CARTOGRAPHER_SYMMETRY_NONE;
+    params.floor_percent = 0.0f;
+    CHECK(cartographer_new_with_params("drunkard_walk_map_open_area", 60, 40, &params) == NULL, "no floor accepted");
+    CHECK(cartographer_new_with_params("drunkard_walk_map_open_area", 60, 40, NULL) == NULL, "NULL params accepted");
+
+    size_t n = cartographer_generator_count();
+    for (size_t i = 0; i < n; i++) {
+        check_generator(cartographer_generator_name(i));
+    }
+
+    if (failures == 0) {
+        printf("ok: %zu generators\n", n);
+        return 0;
+    }
+    fprintf(stderr, "%d failures\n", failures);
+    return 1;
+}
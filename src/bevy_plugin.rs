@@ -0,0 +1,184 @@
+//! Bevy integration
+//!
+//! `CartographerPlugin` builds a map on startup and again whenever a `GenerateMap` event is
+//! sent. Every tile becomes an entity under a single `MapRoot`, carrying a `MapTile` and a
+//! `Transform` laid out by `MapConfig::tile_size`, so a game only has to attach its own
+//! sprites or tilemap to `Added<MapTile>`. Nothing here touches the renderer, so the plugin
+//! runs fine headless.
+
+use bevy::prelude::*;
+
+use crate::maps::utils::{GridType, Map, Position, RandomNumberGenerator, Room, TileType};
+use crate::maps::MapSelector;
+
+/// What to generate. Change it at runtime and send `GenerateMap` to rebuild.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct MapConfig {
+    /// Name of a `MapSelector` generator, see `MapSelector::GENERATORS`
+    pub generator: String,
+    pub width: i32,
+    pub height: i32,
+    /// Seed for the map built on startup; a random one is used if left out
+    pub seed: Option<u64>,
+    /// World units between the centres of neighbouring tiles
+    pub tile_size: f32
+}
+
+impl Default for MapConfig {
+    fn default() -> Self {
+        Self {
+            generator: "basic_map".to_string(),
+            width: 80,
+            height: 50,
+            seed: None,
+            tile_size: 16.0
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CartographerPlugin {
+    pub config: MapConfig
+}
+
+impl Plugin for CartographerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.config.clone())
+            .add_event::<GenerateMap>()
+            .add_event::<MapGenerated>()
+            .add_systems(Startup, request_first_map)
+            .add_systems(Update, generate_map);
+    }
+}
+
+/// Asks for the map to be rebuilt from `MapConfig`, replacing the current one
+#[derive(Event, Debug, Default, Copy, Clone, PartialEq)]
+pub struct GenerateMap {
+    /// A random seed is used if left out
+    pub seed: Option<u64>
+}
+
+/// Sent once a new map has been built and its entities queued for spawning
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+pub struct MapGenerated {
+    pub seed: u64
+}
+
+/// The most recently generated map
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct GeneratedMap {
+    pub map: Map,
+    pub seed: u64,
+    pub exit: Option<Position>
+}
+
+impl GeneratedMap {
+    pub fn start(&self) -> Position {
+        self.map.start_position
+    }
+
+    /// Rooms carved by room based generators; empty for the others
+    pub fn rooms(&self) -> &[Room] {
+        &self.map.rooms
+    }
+}
+
+/// Parent of every entity spawned for a map, so the whole map can be despawned at once
+#[derive(Component, Debug, Default, Copy, Clone)]
+pub struct MapRoot;
+
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub struct MapTile {
+    pub position: Position,
+    pub tile: TileType
+}
+
+/// Marks the tile entity the player starts on
+#[derive(Component, Debug, Default, Copy, Clone)]
+pub struct MapStart;
+
+/// Marks the exit tile entity
+#[derive(Component, Debug, Default, Copy, Clone)]
+pub struct MapExit;
+
+/// A room, placed at its centre
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub struct MapRoom(pub Room);
+
+/// World position of a tile's centre. Rows run down the screen, so y is negated; on hex
+/// grids odd rows are shifted half a tile across and rows are packed closer together.
+pub fn tile_translation(map: &Map, x: i32, y: i32, tile_size: f32) -> Vec3 {
+    match map.grid {
+        GridType::Square => Vec3::new(x as f32 * tile_size, -y as f32 * tile_size, 0.0),
+        GridType::Hex => {
+            let shift = if y % 2 == 1 { 0.5 } else { 0.0 };
+            let row_height = tile_size * 3f32.sqrt() / 2.0;
+            Vec3::new((x as f32 + shift) * tile_size, -y as f32 * row_height, 0.0)
+        }
+    }
+}
+
+fn request_first_map(config: Res<MapConfig>, mut requests: EventWriter<GenerateMap>) {
+    requests.send(GenerateMap { seed: config.seed });
+}
+
+fn generate_map(
+    mut commands: Commands,
+    config: Res<MapConfig>,
+    mut requests: EventReader<GenerateMap>,
+    mut generated: EventWriter<MapGenerated>,
+    roots: Query<Entity, With<MapRoot>>
+) {
+    // Only the latest request matters if several arrive in one frame
+    let Some(request) = requests.read().last().copied() else { return };
+
+    let Some(min_size) = MapSelector::min_size(&config.generator) else {
+        error!("unknown map generator '{}'", config.generator);
+        return;
+    };
+    if config.width < min_size || config.height < min_size {
+        error!("map size {}x{} is too small for {}, both sides need to be at least {}", config.width, config.height, config.generator, min_size);
+        return;
+    }
+    let Some(mut architect) = MapSelector::by_name(&config.generator, config.width, config.height) else {
+        error!("unknown map generator '{}'", config.generator);
+        return;
+    };
+
+    let seed = request.seed.unwrap_or_else(|| RandomNumberGenerator::new().next_u64());
+    architect.build_with_seed(seed);
+    let map = architect.get_map().clone();
+
+    for root in roots.iter() {
+        commands.entity(root).despawn_recursive();
+    }
+    spawn_map(&mut commands, &map, config.tile_size);
+
+    let exit = map.tiles.iter()
+        .position(|t| *t == TileType::Exit)
+        .map(|idx| Position::new(idx as i32 % map.width, idx as i32 / map.width));
+    commands.insert_resource(GeneratedMap { map, seed, exit });
+    generated.send(MapGenerated { seed });
+}
+
+fn spawn_map(commands: &mut Commands, map: &Map, tile_size: f32) {
+    let start_idx = map.xy_idx(map.start_position.x, map.start_position.y);
+
+    commands.spawn((MapRoot, Transform::default())).with_children(|parent| {
+        for (idx, tile) in map.tiles.iter().enumerate() {
+            let position = Position::new(idx as i32 % map.width, idx as i32 / map.width);
+            let mut entity = parent.spawn((
+                MapTile { position, tile: *tile },
+                Transform::from_translation(tile_translation(map, position.x, position.y, tile_size))
+            ));
+
+            if idx == start_idx { entity.insert(MapStart); }
+            if *tile == TileType::Exit { entity.insert(MapExit); }
+        }
+
+        for room in map.rooms.iter() {
+            let (x, y) = room.center();
+            parent.spawn((MapRoom(*room), Transform::from_translation(tile_translation(map, x, y, tile_size))));
+        }
+    });
+}
@@ -0,0 +1,420 @@
+//! Interactive terminal viewer
+//!
+//! Builds a map with any of the `MapSelector` generators while recording its history, then
+//! plays the generation back step by step. The drunkard's walk and DLA generators also have
+//! their floor target, brush size and symmetry tweakable as it runs. The key bindings are
+//! listed down the side.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use bracket_pathfinding::prelude::DijkstraMap;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use cartographer_rs::maps::dla::DlaMap;
+use cartographer_rs::maps::drunkard_walk::DrunkardWalkMap;
+use cartographer_rs::maps::stats::MapStats;
+use cartographer_rs::maps::utils::{GridType, Map, RandomNumberGenerator, Symmetry, TileType};
+use cartographer_rs::maps::{Architect, MapSelector};
+
+const MIN_DELAY: u64 = 5;
+const MAX_DELAY: u64 = 1000;
+
+const HELP: &[(&str, &str)] = &[
+    ("space", "play / pause"),
+    ("← →", "step back / forward"),
+    ("home end", "first / last step"),
+    ("r", "regenerate with a new seed"),
+    ("n p", "next / previous generator"),
+    ("w W", "narrower / wider"),
+    ("h H", "shorter / taller"),
+    ("+ -", "faster / slower"),
+    ("f F", "less / more floor"),
+    ("b B", "smaller / bigger brush"),
+    ("s", "next symmetry"),
+    ("d", "distance field overlay"),
+    ("o", "start and exit markers"),
+    ("q", "quit")
+];
+
+const SYMMETRIES: &[Symmetry] = &[
+    Symmetry::None,
+    Symmetry::Horizontal,
+    Symmetry::Vertical,
+    Symmetry::Both,
+    Symmetry::Rotational2,
+    Symmetry::Rotational4
+];
+
+/// The settings which can be changed live, for generators which have them
+#[derive(Copy, Clone, PartialEq)]
+struct Settings {
+    floor_percent: f32,
+    /// `None` for diggers whose brushes come from their species
+    brush_size: Option<i32>,
+    symmetry: Symmetry
+}
+
+/// A generator whose settings can be changed before it's built
+enum Tweakable {
+    Drunkard(DrunkardWalkMap),
+    Dla(DlaMap)
+}
+
+impl Tweakable {
+    fn by_name(name: &str, width: i32, height: i32) -> Option<Self> {
+        MapSelector::drunkard_by_name(name, width, height).map(Tweakable::Drunkard)
+            .or_else(|| MapSelector::dla_by_name(name, width, height).map(Tweakable::Dla))
+    }
+
+    fn settings(&self) -> Settings {
+        match self {
+            Tweakable::Drunkard(m) => Settings {
+                floor_percent: m.settings.floor_percent,
+                brush_size: m.settings.species.is_empty().then_some(m.settings.brush_size),
+                symmetry: m.settings.symmetry
+            },
+            Tweakable::Dla(m) => Settings {
+                floor_percent: m.settings.floor_percent,
+                brush_size: Some(m.settings.brush_size),
+                symmetry: m.settings.symmetry
+            }
+        }
+    }
+
+    fn with_settings(self, settings: Settings) -> Box<dyn Architect> {
+        match self {
+            Tweakable::Drunkard(mut m) => {
+                m.settings.floor_percent = settings.floor_percent;
+                m.settings.brush_size = settings.brush_size.unwrap_or(m.settings.brush_size);
+                m.settings.symmetry = settings.symmetry;
+                Box::new(m)
+            }
+            Tweakable::Dla(mut m) => {
+                m.settings.floor_percent = settings.floor_percent;
+                m.settings.brush_size = settings.brush_size.unwrap_or(m.settings.brush_size);
+                m.settings.symmetry = settings.symmetry;
+                Box::new(m)
+            }
+        }
+    }
+}
+
+struct Viewer {
+    generator: usize,
+    width: i32,
+    height: i32,
+    seed: u64,
+    /// Live settings for the current generator, if it has any
+    settings: Option<Settings>,
+    /// The finished map, which the history frames are played back over
+    map: Map,
+    frames: Vec<Vec<TileType>>,
+    frame: usize,
+    playing: bool,
+    delay: u64,
+    show_distance: bool,
+    show_markers: bool,
+    rng: RandomNumberGenerator
+}
+
+impl Viewer {
+    fn new() -> Self {
+        let mut rng = RandomNumberGenerator::new();
+        let seed = rng.next_u64();
+        let mut viewer = Self {
+            generator: 0,
+            width: 80,
+            height: 40,
+            seed,
+            settings: None,
+            map: Map::new(0, 0),
+            frames: Vec::new(),
+            frame: 0,
+            playing: true,
+            delay: 50,
+            show_distance: false,
+            show_markers: true,
+            rng
+        };
+        viewer.select(0);
+        viewer
+    }
+
+    fn generator_name(&self) -> &'static str {
+        MapSelector::GENERATORS[self.generator].name
+    }
+
+    /// Switches generator, starting from its own settings
+    fn select(&mut self, generator: usize) {
+        self.generator = generator;
+        self.settings = Tweakable::by_name(self.generator_name(), self.width, self.height).map(|t| t.settings());
+        self.rebuild();
+    }
+
+    /// Builds the current generator from scratch and rewinds to its first step
+    fn rebuild(&mut self) {
+        let tweakable = Tweakable::by_name(self.generator_name(), self.width, self.height);
+        let mut architect = match (tweakable, self.settings) {
+            (Some(tweakable), Some(settings)) => tweakable.with_settings(settings),
+            _ => MapSelector::by_name(self.generator_name(), self.width, self.height)
+                .expect("every listed generator can be built by name")
+        };
+        architect.get_map_mut().record_history();
+        architect.build_with_seed(self.seed);
+
+        self.map = architect.get_map().clone();
+        self.frames = self.map.history.take().unwrap_or_default();
+        if self.frames.is_empty() {
+            self.frames.push(self.map.tiles.clone());
+        }
+        self.frame = 0;
+        self.playing = true;
+    }
+
+    /// The map as it stood at the current step
+    fn current_map(&self) -> Map {
+        let mut map = self.map.clone();
+        map.tiles = self.frames[self.frame].clone();
+        map
+    }
+
+    fn last_frame(&self) -> usize {
+        self.frames.len() - 1
+    }
+
+    fn tick(&mut self) {
+        if self.frame < self.last_frame() {
+            self.frame += 1;
+        } else {
+            self.playing = false;
+        }
+    }
+
+    /// Applies a key press, returning false once the viewer should close
+    fn handle_key(&mut self, key: KeyCode) -> bool {
+        let generators = MapSelector::GENERATORS.len();
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char(' ') => {
+                if !self.playing && self.frame == self.last_frame() {
+                    self.frame = 0;
+                }
+                self.playing = !self.playing;
+            }
+            KeyCode::Left => {
+                self.playing = false;
+                self.frame = self.frame.saturating_sub(1);
+            }
+            KeyCode::Right => {
+                self.playing = false;
+                self.frame = usize::min(self.frame + 1, self.last_frame());
+            }
+            KeyCode::Home => self.frame = 0,
+            KeyCode::End => self.frame = self.last_frame(),
+            KeyCode::Char('r') => {
+                self.seed = self.rng.next_u64();
+                self.rebuild();
+            }
+            KeyCode::Char('n') => self.select((self.generator + 1) % generators),
+            KeyCode::Char('p') => self.select((self.generator + generators - 1) % generators),
+            KeyCode::Char('w') => self.resize(-10, 0),
+            KeyCode::Char('W') => self.resize(10, 0),
+            KeyCode::Char('h') => self.resize(0, -5),
+            KeyCode::Char('H') => self.resize(0, 5),
+            KeyCode::Char('+') | KeyCode::Char('=') => self.delay = u64::max(MIN_DELAY, self.delay / 2),
+            KeyCode::Char('-') => self.delay = u64::min(MAX_DELAY, self.delay * 2),
+            KeyCode::Char('f') => self.tweak(|s| s.floor_percent = f32::max(0.05, s.floor_percent - 0.05)),
+            KeyCode::Char('F') => self.tweak(|s| s.floor_percent = f32::min(0.9, s.floor_percent + 0.05)),
+            KeyCode::Char('b') => self.tweak(|s| s.brush_size = s.brush_size.map(|b| i32::max(1, b - 1))),
+            KeyCode::Char('B') => self.tweak(|s| s.brush_size = s.brush_size.map(|b| i32::min(5, b + 1))),
+            KeyCode::Char('s') => self.tweak(|s| {
+                let current = SYMMETRIES.iter().position(|m| *m == s.symmetry).unwrap_or(0);
+                s.symmetry = SYMMETRIES[(current + 1) % SYMMETRIES.len()];
+            }),
+            KeyCode::Char('d') => self.show_distance = !self.show_distance,
+            KeyCode::Char('o') => self.show_markers = !self.show_markers,
+            _ => {}
+        }
+        true
+    }
+
+    /// Changes the live settings and rebuilds with them, if the generator has any
+    fn tweak(&mut self, change: impl FnOnce(&mut Settings)) {
+        let Some(mut settings) = self.settings else { return };
+        change(&mut settings);
+        if Some(settings) != self.settings {
+            self.settings = Some(settings);
+            self.rebuild();
+        }
+    }
+
+    fn resize(&mut self, dw: i32, dh: i32) {
+        // Big enough for every generator, so switching to another never breaks it
+        let width = i32::max(MapSelector::MIN_SIZE, self.width + dw);
+        let height = i32::max(MapSelector::MIN_SIZE, self.height + dh);
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.rebuild();
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, Viewer::new());
+    ratatui::restore();
+    result
+}
+
+fn run(terminal: &mut DefaultTerminal, mut viewer: Viewer) -> io::Result<()> {
+    let mut last_tick = Instant::now();
+    loop {
+        terminal.draw(|f| draw(f, &viewer))?;
+
+        let delay = Duration::from_millis(viewer.delay);
+        let timeout = if viewer.playing { delay.saturating_sub(last_tick.elapsed()) } else { Duration::from_millis(250) };
+
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !viewer.handle_key(key.code) {
+                    return Ok(());
+                }
+            }
+        }
+
+        if viewer.playing && last_tick.elapsed() >= delay {
+            viewer.tick();
+            last_tick = Instant::now();
+        }
+    }
+}
+
+fn draw(f: &mut Frame, viewer: &Viewer) {
+    let [map_area, side_area] = Layout::horizontal([Constraint::Min(0), Constraint::Length(34)]).areas(f.area());
+    let [stats_area, help_area] = Layout::vertical([Constraint::Length(18), Constraint::Min(0)]).areas(side_area);
+
+    let map = viewer.current_map();
+    let distances = viewer.show_distance.then(|| distance_field(&map));
+
+    let title = format!(" {} ", viewer.generator_name());
+    f.render_widget(Paragraph::new(map_lines(viewer, &map, distances.as_deref())).block(Block::bordered().title(title)), map_area);
+    f.render_widget(Paragraph::new(stats_lines(viewer, &map)).block(Block::bordered().title(" stats ")), stats_area);
+
+    let help: Vec<Line> = HELP.iter()
+        .map(|(keys, action)| Line::from(vec![
+            Span::styled(format!("{:>9} ", keys), Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(*action)
+        ]))
+        .collect();
+    f.render_widget(Paragraph::new(help).block(Block::bordered().title(" keys ")), help_area);
+}
+
+/// Walking distance from the start to every tile, or `f32::MAX` where it can't be reached
+fn distance_field(map: &Map) -> Vec<f32> {
+    let start_idx = map.xy_idx(map.start_position.x, map.start_position.y);
+    DijkstraMap::new(map.width as usize, map.height as usize, &[start_idx], map, map.tiles.len() as f32).map
+}
+
+fn map_lines<'a>(viewer: &Viewer, map: &Map, distances: Option<&[f32]>) -> Vec<Line<'a>> {
+    let start_idx = map.xy_idx(map.start_position.x, map.start_position.y);
+    let max_distance = distances
+        .map(|d| d.iter().copied().filter(|d| *d < f32::MAX).fold(1.0, f32::max))
+        .unwrap_or(1.0);
+
+    (0..map.height).map(|y| {
+        let mut spans = Vec::with_capacity(map.width as usize * 2 + 1);
+        // Hex tiles take two columns each, with odd rows shifted across by one
+        if map.grid == GridType::Hex && y % 2 == 1 {
+            spans.push(Span::raw(" "));
+        }
+
+        for x in 0..map.width {
+            let idx = map.xy_idx(x, y);
+            let tile = map.tiles[idx];
+            let (glyph, mut style) = match tile {
+                TileType::Wall => ('#', Style::default().fg(Color::DarkGray)),
+                TileType::Floor => ('.', Style::default().fg(Color::Gray)),
+                TileType::Exit => ('.', Style::default().fg(Color::Gray)),
+                TileType::Void => (' ', Style::default())
+            };
+            let mut glyph = glyph;
+
+            if let Some(distances) = distances {
+                if tile != TileType::Wall && distances[idx] < f32::MAX {
+                    style = style.bg(gradient(distances[idx] / max_distance)).fg(Color::Black);
+                }
+            }
+
+            if viewer.show_markers {
+                if idx == start_idx && tile != TileType::Wall {
+                    glyph = '@';
+                    style = style.fg(Color::LightGreen).add_modifier(Modifier::BOLD);
+                } else if tile == TileType::Exit {
+                    glyph = 'E';
+                    style = style.fg(Color::LightRed).add_modifier(Modifier::BOLD);
+                }
+            }
+
+            spans.push(Span::styled(glyph.to_string(), style));
+            if map.grid == GridType::Hex {
+                spans.push(Span::styled(" ", style));
+            }
+        }
+
+        Line::from(spans)
+    }).collect()
+}
+
+/// Blue near the start through to yellow at the furthest reachable tile
+fn gradient(t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    Color::Rgb((40.0 + 215.0 * t) as u8, (80.0 + 150.0 * t) as u8, (200.0 - 170.0 * t) as u8)
+}
+
+fn stats_lines<'a>(viewer: &Viewer, map: &Map) -> Vec<Line<'a>> {
+    let stats = MapStats::from_map(map);
+    let distance = match stats.start_exit_distance {
+        Some(d) => format!("{:.0}", d),
+        None => "-".to_string()
+    };
+    let grid = match map.grid {
+        GridType::Square => "square",
+        GridType::Hex => "hex"
+    };
+    let state = if viewer.playing { "playing" } else { "paused" };
+
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    let (target, brush, symmetry) = match viewer.settings {
+        Some(s) => (
+            format!("{:.0}%", s.floor_percent * 100.0),
+            s.brush_size.map_or("-".to_string(), |b| b.to_string()),
+            format!("{:?}", s.symmetry)
+        ),
+        None => ("-".to_string(), "-".to_string(), "-".to_string())
+    };
+    vec![
+        Line::from(format!("seed      {}", viewer.seed)),
+        Line::from(format!("size      {}x{}", map.width, map.height)),
+        Line::from(format!("grid      {}", grid)),
+        Line::from(format!("step      {}/{}", viewer.frame + 1, viewer.frames.len())),
+        Line::from(format!("state     {}", state)),
+        Line::from(format!("delay     {}ms", viewer.delay)),
+        Line::from(""),
+        Line::from(format!("floor     {:.1}%", stats.floor_ratio * 100.0)),
+        Line::from(format!("regions   {}", stats.region_count)),
+        Line::from(format!("to exit   {}", distance)),
+        Line::from(format!("distance  {}", on_off(viewer.show_distance))),
+        Line::from(format!("markers   {}", on_off(viewer.show_markers))),
+        Line::from(""),
+        Line::from(format!("target    {}", target)),
+        Line::from(format!("brush     {}", brush)),
+        Line::from(format!("symmetry  {}", symmetry))
+    ]
+}
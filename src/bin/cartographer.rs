@@ -0,0 +1,337 @@
+//! Command line map generator
+//!
+//! Builds a map with any of the `MapSelector` generators and writes it out as ASCII, JSON,
+//! PNG or a Tiled JSON map, or prints some statistics about it.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, ValueEnum};
+use serde_json::json;
+
+use cartographer_rs::maps::random::{RandomSelector, Tag};
+use cartographer_rs::maps::stats::MapStats;
+use cartographer_rs::maps::utils::{GridType, Map, RandomNumberGenerator, TileType};
+use cartographer_rs::maps::MapSelector;
+
+#[derive(Copy, Clone, PartialEq, ValueEnum)]
+enum Format {
+    Ascii,
+    Json,
+    Png,
+    Tiled
+}
+
+#[derive(Parser)]
+#[command(name = "cartographer", about = "Generate procedural maps")]
+struct Args {
+    /// Generator to use; see --list
+    #[arg(default_value = "basic_map")]
+    generator: String,
+
+    #[arg(short = 'W', long, default_value_t = 80)]
+    width: i32,
+
+    #[arg(short = 'H', long, default_value_t = 50)]
+    height: i32,
+
+    /// Seed for the random number generator; a random seed is used if left out
+    #[arg(short, long)]
+    seed: Option<u64>,
+
+    #[arg(short, long, value_enum, default_value_t = Format::Ascii)]
+    format: Format,
+
+    /// File to write to instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Pixels per tile for PNG output
+    #[arg(long, default_value_t = 4)]
+    scale: u32,
+
+    /// Print floor ratio, region count and start to exit distance instead of the map
+    #[arg(long)]
+    stats: bool,
+
+    /// List the available generators and exit
+    #[arg(long)]
+    list: bool
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    if args.list {
+        for name in MapSelector::names() {
+            println!("{}", name);
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let Some(min_size) = MapSelector::min_size(&args.generator) else {
+        eprintln!("unknown generator '{}', use --list to see them all", args.generator);
+        return ExitCode::FAILURE;
+    };
+    if args.width < min_size || args.height < min_size {
+        eprintln!("{} needs a width and height of at least {}", args.generator, min_size);
+        return ExitCode::FAILURE;
+    }
+
+    let mut seed = args.seed.unwrap_or_else(|| RandomNumberGenerator::new().next_u64());
+    let mut generator = args.generator.clone();
+
+    // Pick from the seed, then report the pick, so the map can be asked for by name again
+    if generator == "random_map" {
+        let mut rng = RandomNumberGenerator::seeded(seed);
+        let selector = RandomSelector::all().without(Tag::Hex);
+        let Some(choice) = selector.choose(args.width, args.height, &mut rng) else {
+            eprintln!("no generator copes with a {}x{} map", args.width, args.height);
+            return ExitCode::FAILURE;
+        };
+        eprintln!("random_map picked {} with seed {}", choice.name, choice.seed);
+        generator = choice.name;
+        seed = choice.seed;
+    }
+
+    let Some(mut architect) = MapSelector::by_name(&generator, args.width, args.height) else {
+        eprintln!("unknown generator '{}', use --list to see them all", generator);
+        return ExitCode::FAILURE;
+    };
+
+    architect.build_with_seed(seed);
+    let map = architect.get_map();
+
+    let output = if args.stats {
+        Ok(render_stats(map, &generator, seed).into_bytes())
+    } else {
+        match args.format {
+            Format::Ascii => Ok(render_ascii(map).into_bytes()),
+            Format::Json => Ok(render_json(map, &generator, seed).into_bytes()),
+            Format::Png => render_png(map, args.scale.max(1)),
+            Format::Tiled => Ok(render_tiled(map).into_bytes())
+        }
+    };
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("failed to render map: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let written = match &args.output {
+        Some(path) => File::create(path).and_then(|mut f| f.write_all(&output)),
+        None => io::stdout().write_all(&output)
+    };
+
+    if let Err(e) = written {
+        eprintln!("failed to write map: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn tile_char(map: &Map, idx: usize) -> char {
+    let start_idx = map.xy_idx(map.start_position.x, map.start_position.y);
+    if idx == start_idx { return '@'; }
+
+    match map.tiles[idx] {
+        TileType::Wall => '#',
+        TileType::Floor => '.',
+        TileType::Exit => 'E',
+        TileType::Void => ' '
+    }
+}
+
+fn rows(map: &Map) -> Vec<String> {
+    (0..map.height)
+        .map(|y| (0..map.width).map(|x| tile_char(map, map.xy_idx(x, y))).collect())
+        .collect()
+}
+
+fn render_ascii(map: &Map) -> String {
+    let mut out = String::new();
+    for (y, row) in rows(map).iter().enumerate() {
+        // Shift odd hex rows across so they sit between the rows around them
+        if map.grid == GridType::Hex {
+            if y % 2 == 1 { out.push(' '); }
+            let spaced: Vec<String> = row.chars().map(String::from).collect();
+            out.push_str(&spaced.join(" "));
+        } else {
+            out.push_str(row);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn grid_name(map: &Map) -> &'static str {
+    match map.grid {
+        GridType::Square => "square",
+        GridType::Hex => "hex"
+    }
+}
+
+fn render_json(map: &Map, generator: &str, seed: u64) -> String {
+    let value = json!({
+        "generator": generator,
+        "seed": seed,
+        "width": map.width,
+        "height": map.height,
+        "grid": grid_name(map),
+        "start": { "x": map.start_position.x, "y": map.start_position.y },
+        "legend": { "#": "wall", ".": "floor", "E": "exit", "@": "start", " ": "void" },
+        "rows": rows(map)
+    });
+
+    let mut out = serde_json::to_string_pretty(&value).unwrap();
+    out.push('\n');
+    out
+}
+
+fn render_stats(map: &Map, generator: &str, seed: u64) -> String {
+    let stats = MapStats::from_map(map);
+    let distance = match stats.start_exit_distance {
+        Some(d) => format!("{:.0}", d),
+        None => "unreachable".to_string()
+    };
+
+    format!(
+        "generator: {}\nseed: {}\nsize: {}x{}\nfloor ratio: {:.3}\nregions: {}\nstart to exit: {}\n",
+        generator, seed, map.width, map.height, stats.floor_ratio, stats.region_count, distance
+    )
+}
+
+/// Fails if the scaled image is too big to hold in memory or to describe in a PNG header
+fn render_png(map: &Map, scale: u32) -> Result<Vec<u8>, String> {
+    let too_big = || format!("a {}x{} map at scale {} is too big for a PNG", map.width, map.height, scale);
+    let width = (map.width as u32).checked_mul(scale).ok_or_else(too_big)?;
+    let height = (map.height as u32).checked_mul(scale).ok_or_else(too_big)?;
+    let len = (width as usize).checked_mul(height as usize)
+        .and_then(|n| n.checked_mul(3))
+        .ok_or_else(too_big)?;
+    let start_idx = map.xy_idx(map.start_position.x, map.start_position.y);
+
+    let mut pixels = Vec::new();
+    pixels.try_reserve_exact(len).map_err(|_| too_big())?;
+    for py in 0..height {
+        for px in 0..width {
+            let idx = map.xy_idx((px / scale) as i32, (py / scale) as i32);
+            let colour: [u8; 3] = match map.tiles[idx] {
+                _ if idx == start_idx => [60, 200, 80],
+                TileType::Wall => [40, 40, 48],
+                TileType::Floor => [200, 190, 170],
+                TileType::Exit => [220, 60, 60],
+                TileType::Void => [0, 0, 0]
+            };
+            pixels.extend_from_slice(&colour);
+        }
+    }
+
+    let mut out = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+        writer.write_image_data(&pixels).map_err(|e| e.to_string())?;
+    }
+    Ok(out)
+}
+
+/// A Tiled JSON map with a single tile layer and the start position as a point object.
+/// Tile ids are 1 = wall, 2 = floor, 3 = exit, 4 = void, for a 4 tile tileset.
+fn render_tiled(map: &Map) -> String {
+    const TILE_SIZE: i32 = 16;
+
+    let data: Vec<u32> = map.tiles.iter().map(|t| match t {
+        TileType::Wall => 1,
+        TileType::Floor => 2,
+        TileType::Exit => 3,
+        TileType::Void => 4
+    }).collect();
+
+    let mut value = json!({
+        "type": "map",
+        "version": "1.10",
+        "tiledversion": "1.10.2",
+        "orientation": "orthogonal",
+        "renderorder": "right-down",
+        "infinite": false,
+        "width": map.width,
+        "height": map.height,
+        "tilewidth": TILE_SIZE,
+        "tileheight": TILE_SIZE,
+        "nextlayerid": 3,
+        "nextobjectid": 2,
+        "layers": [
+            {
+                "id": 1,
+                "name": "tiles",
+                "type": "tilelayer",
+                "x": 0,
+                "y": 0,
+                "width": map.width,
+                "height": map.height,
+                "opacity": 1,
+                "visible": true,
+                "data": data
+            },
+            {
+                "id": 2,
+                "name": "markers",
+                "type": "objectgroup",
+                "draworder": "topdown",
+                "x": 0,
+                "y": 0,
+                "opacity": 1,
+                "visible": true,
+                "objects": [
+                    {
+                        "id": 1,
+                        "name": "start",
+                        "type": "start",
+                        "point": true,
+                        "x": map.start_position.x * TILE_SIZE + TILE_SIZE / 2,
+                        "y": map.start_position.y * TILE_SIZE + TILE_SIZE / 2,
+                        "width": 0,
+                        "height": 0,
+                        "rotation": 0,
+                        "visible": true
+                    }
+                ]
+            }
+        ],
+        "tilesets": [
+            {
+                "firstgid": 1,
+                "name": "cartographer",
+                "tilecount": 4,
+                "columns": 4,
+                "tilewidth": TILE_SIZE,
+                "tileheight": TILE_SIZE,
+                "margin": 0,
+                "spacing": 0,
+                "image": "cartographer.png",
+                "imagewidth": TILE_SIZE * 4,
+                "imageheight": TILE_SIZE
+            }
+        ]
+    });
+
+    // Tiled's staggered hexagonal layout with odd rows shifted matches our odd-r hex grid
+    if map.grid == GridType::Hex {
+        value["orientation"] = json!("hexagonal");
+        value["staggeraxis"] = json!("y");
+        value["staggerindex"] = json!("odd");
+        value["hexsidelength"] = json!(TILE_SIZE / 2);
+    }
+
+    let mut out = serde_json::to_string_pretty(&value).unwrap();
+    out.push('\n');
+    out
+}
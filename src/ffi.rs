@@ -0,0 +1,360 @@
+//! C interface
+//!
+//! A small C ABI over `MapSelector::by_name`, for callers outside Rust. Generators are handed
+//! out as opaque `CartographerMap` pointers which must be released with `cartographer_free`.
+//! The drunkard's walk and DLA generators can also be created with their floor target, brush
+//! size and symmetry changed, through `cartographer_new_with_params`.
+//! The matching header lives in `ffi/cartographer.h` and is generated with cbindgen.
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::OnceLock;
+
+use crate::maps::utils::{GridType, Map, Symmetry, TileType};
+use crate::maps::dla::DlaMap;
+use crate::maps::drunkard_walk::DrunkardWalkMap;
+use crate::maps::{Architect, MapSelector};
+
+/// Width and height big enough for every generator; `cartographer_min_size` gives each
+/// generator's own minimum
+pub const CARTOGRAPHER_MIN_SIZE: i32 = 24;
+
+// cbindgen needs a plain number for the header, so make sure it's the same one
+const _: () = assert!(CARTOGRAPHER_MIN_SIZE == MapSelector::MIN_SIZE);
+
+/// A generator and the map it builds
+pub struct CartographerMap {
+    architect: Box<dyn Architect>
+}
+
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CartographerTile {
+    Wall = 0,
+    Floor = 1,
+    Exit = 2,
+    Void = 3
+}
+
+impl From<TileType> for CartographerTile {
+    fn from(tile: TileType) -> Self {
+        match tile {
+            TileType::Wall => CartographerTile::Wall,
+            TileType::Floor => CartographerTile::Floor,
+            TileType::Exit => CartographerTile::Exit,
+            TileType::Void => CartographerTile::Void
+        }
+    }
+}
+
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CartographerGrid {
+    Square = 0,
+    Hex = 1
+}
+
+/// Values for `CartographerParams::symmetry`
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CartographerSymmetry {
+    None = 0,
+    Horizontal = 1,
+    Vertical = 2,
+    Both = 3,
+    Rotational2 = 4,
+    Rotational4 = 5,
+    /// `radial_folds` copies spaced evenly around the centre
+    Radial = 6
+}
+
+/// Settings shared by the drunkard's walk and DLA generators
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CartographerParams {
+    /// Fraction of the map to turn into floor, above 0 and at most 1
+    pub floor_percent: f32,
+    /// Size of the digging brush, at least 1. Drunkard presets with several kinds of digger
+    /// keep their own brushes.
+    pub brush_size: i32,
+    /// One of the `CartographerSymmetry` values
+    pub symmetry: u8,
+    /// Copies around the centre for `CARTOGRAPHER_SYMMETRY_RADIAL`, otherwise ignored
+    pub radial_folds: u32
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CartographerPosition {
+    pub x: i32,
+    pub y: i32
+}
+
+/// A room as the corners `(x1, y1)` to `(x2, y2)`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CartographerRoom {
+    pub x1: i32,
+    pub y1: i32,
+    pub x2: i32,
+    pub y2: i32
+}
+
+/// A generator which takes `CartographerParams`
+enum Configurable {
+    Drunkard(DrunkardWalkMap),
+    Dla(DlaMap)
+}
+
+impl Configurable {
+    fn by_name(name: &str, width: i32, height: i32) -> Option<Self> {
+        MapSelector::drunkard_by_name(name, width, height).map(Configurable::Drunkard)
+            .or_else(|| MapSelector::dla_by_name(name, width, height).map(Configurable::Dla))
+    }
+
+    fn params(&self) -> CartographerParams {
+        let (floor_percent, brush_size, symmetry) = match self {
+            Configurable::Drunkard(m) => (m.settings.floor_percent, m.settings.brush_size, m.settings.symmetry),
+            Configurable::Dla(m) => (m.settings.floor_percent, m.settings.brush_size, m.settings.symmetry)
+        };
+        let (symmetry, radial_folds) = match symmetry {
+            Symmetry::None => (CartographerSymmetry::None, 0),
+            Symmetry::Horizontal => (CartographerSymmetry::Horizontal, 0),
+            Symmetry::Vertical => (CartographerSymmetry::Vertical, 0),
+            Symmetry::Both => (CartographerSymmetry::Both, 0),
+            Symmetry::Rotational2 => (CartographerSymmetry::Rotational2, 0),
+            Symmetry::Rotational4 => (CartographerSymmetry::Rotational4, 0),
+            Symmetry::Radial(folds) => (CartographerSymmetry::Radial, folds)
+        };
+        CartographerParams { floor_percent, brush_size, symmetry: symmetry as u8, radial_folds }
+    }
+
+    /// The generator with `params` applied, or `None` if any of them is out of range
+    fn with_params(self, params: &CartographerParams) -> Option<Box<dyn Architect>> {
+        let symmetry = match params.symmetry {
+            0 => Symmetry::None,
+            1 => Symmetry::Horizontal,
+            2 => Symmetry::Vertical,
+            3 => Symmetry::Both,
+            4 => Symmetry::Rotational2,
+            5 => Symmetry::Rotational4,
+            6 if params.radial_folds > 0 => Symmetry::Radial(params.radial_folds),
+            _ => return None
+        };
+        if !(params.floor_percent > 0.0 && params.floor_percent <= 1.0) || params.brush_size < 1 {
+            return None;
+        }
+
+        Some(match self {
+            Configurable::Drunkard(mut m) => {
+                m.settings.floor_percent = params.floor_percent;
+                m.settings.brush_size = params.brush_size;
+                m.settings.symmetry = symmetry;
+                Box::new(m)
+            }
+            Configurable::Dla(mut m) => {
+                m.settings.floor_percent = params.floor_percent;
+                m.settings.brush_size = params.brush_size;
+                m.settings.symmetry = symmetry;
+                Box::new(m)
+            }
+        })
+    }
+}
+
+fn generator_names() -> &'static [CString] {
+    static NAMES: OnceLock<Vec<CString>> = OnceLock::new();
+    NAMES.get_or_init(|| {
+        MapSelector::names().map(|name| CString::new(name).unwrap()).collect()
+    })
+}
+
+unsafe fn map_ref<'a>(map: *const CartographerMap) -> Option<&'a Map> {
+    map.as_ref().map(|m| m.architect.get_map())
+}
+
+/// Number of generators which `cartographer_new` accepts
+#[no_mangle]
+pub extern "C" fn cartographer_generator_count() -> usize {
+    MapSelector::GENERATORS.len()
+}
+
+/// Name of the generator at `index`, or NULL if it's out of range. The string is owned by the
+/// library and lives for as long as the library stays loaded.
+#[no_mangle]
+pub extern "C" fn cartographer_generator_name(index: usize) -> *const c_char {
+    generator_names().get(index).map_or(ptr::null(), |name| name.as_ptr())
+}
+
+/// Smallest width and height the named generator builds a proper map at, or 0 if the name
+/// is unknown
+///
+/// # Safety
+/// `generator` must be NULL or a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cartographer_min_size(generator: *const c_char) -> i32 {
+    if generator.is_null() { return 0; }
+    let Ok(name) = CStr::from_ptr(generator).to_str() else { return 0 };
+    MapSelector::min_size(name).unwrap_or(0)
+}
+
+/// Creates the named generator for a map of the given size. Returns NULL if the name is
+/// unknown or either dimension is below the generator's `cartographer_min_size`.
+///
+/// # Safety
+/// `generator` must be NULL or a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cartographer_new(generator: *const c_char, width: i32, height: i32) -> *mut CartographerMap {
+    if generator.is_null() { return ptr::null_mut(); }
+
+    let Ok(name) = CStr::from_ptr(generator).to_str() else { return ptr::null_mut() };
+    let Some(min_size) = MapSelector::min_size(name) else { return ptr::null_mut() };
+    if width < min_size || height < min_size {
+        return ptr::null_mut();
+    }
+    match MapSelector::by_name(name, width, height) {
+        Some(architect) => Box::into_raw(Box::new(CartographerMap { architect })),
+        None => ptr::null_mut()
+    }
+}
+
+/// Writes the named generator's usual settings to `out`. Returns false if the name is unknown
+/// or the generator doesn't take `CartographerParams`.
+///
+/// # Safety
+/// `generator` must be NULL or a valid NUL terminated string, and `out` must be NULL or point
+/// to a writable `CartographerParams`.
+#[no_mangle]
+pub unsafe extern "C" fn cartographer_params(generator: *const c_char, out: *mut CartographerParams) -> bool {
+    if generator.is_null() || out.is_null() { return false; }
+    let Ok(name) = CStr::from_ptr(generator).to_str() else { return false };
+    let Some(configurable) = Configurable::by_name(name, 0, 0) else { return false };
+
+    out.write(configurable.params());
+    true
+}
+
+/// Creates the named generator like `cartographer_new`, with its settings replaced by
+/// `params`. Start from `cartographer_params` to change only some of them. Returns NULL
+/// where `cartographer_new` would, if the generator doesn't take `CartographerParams`, or if
+/// any of them is out of range.
+///
+/// # Safety
+/// `generator` must be NULL or a valid NUL terminated string, and `params` must be NULL or
+/// point to a `CartographerParams`.
+#[no_mangle]
+pub unsafe extern "C" fn cartographer_new_with_params(
+    generator: *const c_char,
+    width: i32,
+    height: i32,
+    params: *const CartographerParams
+) -> *mut CartographerMap {
+    if generator.is_null() { return ptr::null_mut(); }
+    let Some(params) = params.as_ref() else { return ptr::null_mut() };
+
+    let Ok(name) = CStr::from_ptr(generator).to_str() else { return ptr::null_mut() };
+    let Some(min_size) = MapSelector::min_size(name) else { return ptr::null_mut() };
+    if width < min_size || height < min_size {
+        return ptr::null_mut();
+    }
+    match Configurable::by_name(name, width, height).and_then(|c| c.with_params(params)) {
+        Some(architect) => Box::into_raw(Box::new(CartographerMap { architect })),
+        None => ptr::null_mut()
+    }
+}
+
+/// Builds the map from `seed`, replacing anything built before. Returns false if the
+/// generator failed, in which case the map contents shouldn't be relied on.
+///
+/// # Safety
+/// `map` must be NULL or a pointer returned by `cartographer_new` which hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cartographer_build(map: *mut CartographerMap, seed: u64) -> bool {
+    let Some(map) = map.as_mut() else { return false };
+    // Unwinding across the C boundary is undefined behaviour, so stop any panic here
+    panic::catch_unwind(AssertUnwindSafe(|| map.architect.build_with_seed(seed))).is_ok()
+}
+
+/// # Safety
+/// `map` must be NULL or a pointer returned by `cartographer_new` which hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cartographer_width(map: *const CartographerMap) -> i32 {
+    map_ref(map).map_or(0, |m| m.width)
+}
+
+/// # Safety
+/// `map` must be NULL or a pointer returned by `cartographer_new` which hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cartographer_height(map: *const CartographerMap) -> i32 {
+    map_ref(map).map_or(0, |m| m.height)
+}
+
+/// # Safety
+/// `map` must be NULL or a pointer returned by `cartographer_new` which hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cartographer_grid(map: *const CartographerMap) -> CartographerGrid {
+    match map_ref(map).map(|m| m.grid) {
+        Some(GridType::Hex) => CartographerGrid::Hex,
+        _ => CartographerGrid::Square
+    }
+}
+
+/// Copies up to `len` tiles, row by row, into `out` and returns the total number of tiles in
+/// the map. Pass a NULL `out` to just ask for the count.
+///
+/// # Safety
+/// `map` must be NULL or a live pointer from `cartographer_new`, and `out` must be NULL or
+/// point to at least `len` writable tiles.
+#[no_mangle]
+pub unsafe extern "C" fn cartographer_tiles(map: *const CartographerMap, out: *mut CartographerTile, len: usize) -> usize {
+    let Some(map) = map_ref(map) else { return 0 };
+    if !out.is_null() {
+        for (i, tile) in map.tiles.iter().take(len).enumerate() {
+            out.add(i).write(CartographerTile::from(*tile));
+        }
+    }
+    map.tiles.len()
+}
+
+/// # Safety
+/// `map` must be NULL or a pointer returned by `cartographer_new` which hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cartographer_start(map: *const CartographerMap) -> CartographerPosition {
+    let (x, y) = map.as_ref().map_or((0, 0), |m| m.architect.start_position());
+    CartographerPosition { x, y }
+}
+
+/// Number of rooms in the map. Only the room based generators produce any.
+///
+/// # Safety
+/// `map` must be NULL or a pointer returned by `cartographer_new` which hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cartographer_room_count(map: *const CartographerMap) -> usize {
+    map_ref(map).map_or(0, |m| m.rooms.len())
+}
+
+/// Writes the room at `index` to `out`, returning false if there's no such room.
+///
+/// # Safety
+/// `map` must be NULL or a live pointer from `cartographer_new`, and `out` must be NULL or
+/// point to a writable `CartographerRoom`.
+#[no_mangle]
+pub unsafe extern "C" fn cartographer_room(map: *const CartographerMap, index: usize, out: *mut CartographerRoom) -> bool {
+    let Some(room) = map_ref(map).and_then(|m| m.rooms.get(index)) else { return false };
+    if out.is_null() { return false; }
+
+    out.write(CartographerRoom { x1: room.x1, y1: room.y1, x2: room.x2, y2: room.y2 });
+    true
+}
+
+/// Releases a generator. Passing NULL does nothing.
+///
+/// # Safety
+/// `map` must be NULL or a pointer returned by `cartographer_new` which hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cartographer_free(map: *mut CartographerMap) {
+    if !map.is_null() {
+        drop(Box::from_raw(map));
+    }
+}
@@ -1,8 +1,39 @@
 pub mod maps;
 
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "python")]
+mod python;
+
+#[cfg(feature = "bevy")]
+pub mod bevy_plugin;
+
 #[cfg(test)]
 mod tests {
     use super::maps::{Architect, MapSelector};
+    use super::maps::batch::{generate_batch, generate_batch_with_progress};
+    use super::maps::random::{RandomChoice, RandomEntry, RandomMap, RandomSelector, Tag};
+    use super::maps::stats::MapStats;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use super::maps::hex::{hex_distance, Hex};
+    use super::maps::basic::{BasicMap, BasicSettings};
+    use super::maps::bsp::bsp::BspMap;
+    use super::maps::bsp::bsp_interior::BspInteriorMap;
+    use super::maps::bsp::tree::{BspSettings, BspTree};
+    use super::maps::composite::{CompositeMap, Partition};
+    use super::maps::dla::{Attractor, DlaAlgorithm, DlaMap};
+    use super::maps::drunkard_walk::{DrunkardSpecies, DrunkardWalkMap};
+    use super::maps::maze::MazeMap;
+    use super::maps::utils::{paint, remove_unreachable_areas_returning_most_distant, GridType, Map, Position, RandomNumberGenerator, Room, TileType};
+    use super::maps::symmetry::Symmetry;
+    use super::maps::draw::{self, Brush};
+    use super::maps::cellular_automata::{CellularAutomataMap, CellularAutomataSettings, InitialFill};
+    use super::maps::cellular_automata::rules::{smooth, Neighbourhood, OutOfBounds, Phase, Rule};
+    use super::maps::transform::{Flip, Rotation};
+    use super::maps::validate::MapProblem;
+    use super::maps::voronoi_cell::{voronoi_membership, voronoi_regions, DistanceAlgorithm, SeedPlacement, VoronoiCellMap, VoronoiSettings};
+    use bracket_pathfinding::prelude::BaseMap;
 
     #[cfg(feature = "debug")]
     use super::maps::utils::output_map;
@@ -16,6 +47,61 @@ mod tests {
         output_map(&m.map, "basic_map.txt");
     }
 
+    #[test]
+    fn build_basic_map_halls() {
+        let mut m = MapSelector::basic_map_halls(80, 50);
+        m.build();
+        assert!(m.map.validate().is_valid());
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "basic_map_halls.txt");
+    }
+
+    #[test]
+    fn build_basic_map_cells() {
+        let mut m = MapSelector::basic_map_cells(80, 50);
+        m.build();
+        assert!(m.map.validate().is_valid());
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "basic_map_cells.txt");
+    }
+
+    #[test]
+    fn basic_map_rooms_follow_the_settings() {
+        let settings = BasicSettings {
+            max_rooms: Some(6),
+            placement_attempts: Some(500),
+            min_width: 4,
+            max_width: 7,
+            min_height: 3,
+            max_height: 5,
+            spacing: 2,
+            fill_edges: false
+        };
+        let mut m = BasicMap::with_settings(80, 50, settings);
+        m.build_with_seed(8);
+        assert_eq!(m.rooms.len(), 6);
+        assert_eq!(m.rooms, m.map.rooms);
+        assert!(m.map.validate().is_valid());
+
+        for (i, room) in m.rooms.iter().enumerate() {
+            // Floors run from x1 + 1 to x2, and stay off the edge without it being filled in
+            assert!((4..=7).contains(&(room.x2 - room.x1)) && (3..=5).contains(&(room.y2 - room.y1)));
+            assert!(room.x1 >= 0 && room.y1 >= 0 && room.x2 <= 78 && room.y2 <= 48);
+            for other in m.rooms.iter().skip(i + 1) {
+                let padded = Room { x1: room.x1 - 2, y1: room.y1 - 2, x2: room.x2 + 2, y2: room.y2 + 2 };
+                assert!(!padded.intersect(other));
+            }
+        }
+
+        // Rooms too big for the map leave a scrap of floor to start on
+        let mut m = BasicMap::with_settings(10, 10, BasicSettings { min_width: 12, max_width: 12, ..settings });
+        m.build_with_seed(8);
+        assert_eq!(m.rooms.len(), 1);
+        assert_ne!(m.map.get_tile(m.map.start_position.x, m.map.start_position.y), TileType::Wall);
+    }
+
     #[test]
     fn build_bsp_map() {
         let mut m = MapSelector::bsp_map(100, 100);
@@ -34,6 +120,62 @@ mod tests {
         output_map(&m.map, "bsp_interior_map.txt");
     }
 
+    #[test]
+    fn bsp_tree_partitions_within_its_limits() {
+        let settings = BspSettings { min_leaf_size: 5, max_leaf_size: 14, min_split_ratio: 0.3, max_split_ratio: 0.7, ..BspSettings::default() };
+        let root = Room { x1: 0, y1: 0, x2: 79, y2: 49 };
+        let tree = BspTree::new(root, &settings, &mut RandomNumberGenerator::seeded(4));
+
+        // The leaves cover every tile exactly once, and are neither too small nor too big
+        let mut covered = vec![0; 80 * 50];
+        for leaf in tree.leaves() {
+            let rect = tree.nodes[leaf].rect;
+            assert!(rect.x2 - rect.x1 + 1 >= 5 && rect.y2 - rect.y1 + 1 >= 5);
+            assert!(rect.x2 - rect.x1 < 14 && rect.y2 - rect.y1 < 14);
+            for y in rect.y1 ..= rect.y2 {
+                for x in rect.x1 ..= rect.x2 { covered[(y * 80 + x) as usize] += 1; }
+            }
+        }
+        assert!(covered.iter().all(|c| *c == 1));
+
+        // Siblings split their parent between them
+        for (first, second) in tree.siblings() {
+            let (a, b) = (tree.nodes[first].rect, tree.nodes[second].rect);
+            let parent = tree.nodes[tree.nodes[first].parent.unwrap()].rect;
+            assert_eq!(tree.nodes[second].parent, tree.nodes[first].parent);
+            assert_eq!(Room { x1: a.x1.min(b.x1), y1: a.y1.min(b.y1), x2: a.x2.max(b.x2), y2: a.y2.max(b.y2) }, parent);
+        }
+
+        // Depth caps the number of cuts
+        let shallow = BspTree::new(root, &BspSettings { max_depth: 2, ..settings }, &mut RandomNumberGenerator::seeded(4));
+        assert_eq!(shallow.leaves().len(), 4);
+        assert!(shallow.nodes.iter().all(|n| n.depth <= 2));
+
+        // BSP maps put one room inside each leaf
+        let mut m = BspMap::with_settings(80, 50, settings);
+        m.build_with_seed(4);
+        assert_eq!(m.rooms.len(), m.tree.leaves().len());
+        for (room, leaf) in m.rooms.iter().zip(m.tree.leaves()) {
+            let rect = m.tree.nodes[leaf].rect;
+            assert!(room.x1 >= rect.x1 && room.x2 < rect.x2 && room.y1 >= rect.y1 && room.y2 < rect.y2);
+        }
+        assert!(m.map.validate().is_valid());
+
+        // Interior maps never cut a leaf too narrow to hold any floor
+        for seed in 0..5 {
+            let mut m = BspInteriorMap::with_settings(40, 30, BspSettings { min_leaf_size: 1, max_leaf_size: 4, ..settings });
+            m.build_with_seed(seed);
+            assert!(m.map.validate().is_valid());
+            assert!(m.rooms.iter().all(|r| r.x2 - r.x1 >= 1 && r.y2 - r.y1 >= 1));
+        }
+
+        // Leaves too small for any room still leave one room to start in
+        let mut m = BspMap::with_settings(40, 30, BspSettings { min_leaf_size: 1, max_leaf_size: 2, ..settings });
+        m.build_with_seed(4);
+        assert_eq!(m.rooms.len(), 1);
+        assert_ne!(m.map.get_tile(m.map.start_position.x, m.map.start_position.y), TileType::Wall);
+    }
+
     #[test]
     fn build_cellular_automata_map() {
         let mut m = MapSelector::cellular_automata_map(100, 100);
@@ -43,6 +185,64 @@ mod tests {
         output_map(&m.map, "cellular_automata_map.txt");
     }
 
+    #[test]
+    fn cellular_automata_rule_notation() {
+        let rule = Rule::parse("B678/S2345678").unwrap();
+        assert_eq!(rule, Rule::new(6..=8, 2..=8));
+        assert_eq!(rule.to_string(), "B678/S2345678");
+        assert_eq!(Rule::parse("s45678/b5678"), Some(Rule::new(5..=8, 4..=8)));
+        assert_eq!(Rule::parse("B13,14/S12").unwrap().to_string(), "B13,14/S12");
+        assert_eq!(Rule::parse("B3/S23/B3"), None);
+        assert_eq!(Rule::parse("B3x/S23"), None);
+        assert_eq!(Rule::parse("B3"), None);
+    }
+
+    #[test]
+    fn build_cellular_automata_map_with_custom_rules() {
+        let settings = CellularAutomataSettings {
+            fill: InitialFill::Noise { wall_percent: 50, scale: 4 },
+            phases: vec![
+                Phase::new(Rule::parse("B5678/S45678").unwrap(), Neighbourhood::Moore(1), 3),
+                Phase::new(Rule::new(7..=12, 6..=12), Neighbourhood::VonNeumann(2), 2)
+            ],
+            out_of_bounds: OutOfBounds::Wrap
+        };
+        let mut m = CellularAutomataMap::with_settings(80, 50, settings.clone());
+        m.build_with_seed(1);
+        assert!(m.map.validate().is_valid());
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "cellular_automata_map_custom_rules.txt");
+
+        // Custom settings survive the switch to a hex grid
+        let m = CellularAutomataMap::with_settings(80, 50, settings.clone()).on_hex_grid();
+        assert_eq!(m.settings, settings);
+    }
+
+    #[test]
+    fn smooth_any_map_within_a_mask() {
+        let mut m = MapSelector::maze_map(80, 50);
+        m.build_with_seed(2);
+        let before = m.map.clone();
+
+        // Erode only the left half of the maze into ruins
+        let mask: Vec<bool> = (0..before.tiles.len()).map(|idx| (idx as i32 % 80) < 40).collect();
+        let erosion = Phase::new(Rule::parse("B678/S5678").unwrap(), Neighbourhood::Moore(1), 2);
+        let changed = smooth(&mut m.map, &erosion, OutOfBounds::Wall, Some(&mask));
+        assert!(!changed.is_empty());
+        assert!(changed.iter().all(|idx| mask[*idx]));
+        assert_eq!(m.map.start_position, before.start_position);
+        assert_eq!(m.map.count_tile_type(TileType::Exit), 1);
+
+        let mut m = MapSelector::bsp_map(80, 50);
+        m.build_with_seed(2);
+        smooth(&mut m.map, &Phase::smoothing(1), OutOfBounds::Wall, None);
+        assert_eq!(m.map.count_tile_type(TileType::Exit), 1);
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "bsp_map_smoothed.txt");
+    }
+
     #[test]
     fn build_drunkard_walk_map_open_area() {
         let mut m = MapSelector::drunkard_walk_map_open_area(80, 50);
@@ -88,6 +288,161 @@ mod tests {
         output_map(&m.map, "drunkard_walk_map_fearful_symmetry.txt");
     }
 
+    #[test]
+    fn build_drunkard_walk_mines() {
+        let mut m = MapSelector::drunkard_walk_mines(80, 50);
+        m.build();
+        assert!(m.map.validate().is_valid());
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "drunkard_walk_mines.txt");
+    }
+
+    #[test]
+    fn build_drunkard_walk_tunnels_and_chambers() {
+        let mut m = MapSelector::drunkard_walk_tunnels_and_chambers(80, 50);
+        m.build();
+        assert!(m.map.validate().is_valid());
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "drunkard_walk_tunnels_and_chambers.txt");
+    }
+
+    #[test]
+    fn drunkard_species_steer_their_diggers() {
+        // Diggers which only ever head right dig a single straight tunnel from the start
+        let mut m = DrunkardWalkMap::open_area(60, 30);
+        m.settings.floor_percent = 0.01;
+        m.settings.species = vec![DrunkardSpecies {
+            direction_weights: vec![0.0, 1.0, 0.0, 0.0],
+            ..DrunkardSpecies::new(Brush::square(0), 100)
+        }];
+        m.build_with_seed(3);
+        let start = m.map.start_position;
+        for x in 0..60 {
+            for y in 0..30 {
+                let walkable = m.map.get_tile(x, y) != TileType::Wall;
+                assert_eq!(walkable, y == start.y && x >= start.x && x <= 58, "tile ({}, {})", x, y);
+            }
+        }
+
+        // Diagonal steps keep the tunnel connected, and branching diggers still finish
+        let mut m = DrunkardWalkMap::mines(60, 40);
+        m.settings.species[0].diagonals = true;
+        m.settings.species.push(DrunkardSpecies::new(Brush::diamond(1), 20));
+        m.build_with_seed(5);
+        assert!(m.map.validate().is_valid());
+        assert!(m.map.count_tile_type(TileType::Floor) as f32 >= 0.3 * 60.0 * 40.0 * 0.9);
+
+        // Diggers which can't reach `floor_percent` give up at the cap
+        let mut m = DrunkardWalkMap::open_area(40, 30);
+        m.settings.lifetime = 0;
+        m.settings.max_diggers = 100;
+        m.build_with_seed(7);
+        assert_eq!(m.map.count_tile_type(TileType::Floor), 0);
+
+        let mut m = DrunkardWalkMap::open_halls(40, 30);
+        m.settings.floor_percent = 1.0;
+        m.settings.max_diggers = 100;
+        m.build_with_seed(7);
+        assert!(m.map.validate().is_valid());
+    }
+
+    #[test]
+    fn unreachable_area_removal_keeps_long_paths() {
+        // A corridor far longer than any fixed search depth keeps its far end
+        let mut map = Map::new(300, 5);
+        for x in 1..299 { map.set_tile(x, 2, TileType::Floor); }
+        map.set_tile(150, 4, TileType::Floor);
+        let start_idx = map.xy_idx(1, 2);
+        let exit = remove_unreachable_areas_returning_most_distant(&mut map, start_idx);
+        assert_eq!(exit, map.xy_idx(298, 2));
+        assert_eq!(map.count_tile_type(TileType::Floor), 298);
+    }
+
+    #[test]
+    fn paint_reports_newly_carved_tiles() {
+        let mut map = Map::new(20, 20);
+
+        let carved = paint(&mut map, Symmetry::None, 1, 5, 5);
+        assert_eq!(carved, vec![map.xy_idx(5, 5)]);
+
+        // Painting over existing floor carves nothing new
+        assert!(paint(&mut map, Symmetry::None, 1, 5, 5).is_empty());
+
+        let carved = paint(&mut map, Symmetry::Horizontal, 1, 4, 8);
+        assert_eq!(carved.len(), 2);
+        assert_eq!(map.count_tile_type(TileType::Floor), 3);
+    }
+
+    #[test]
+    fn draw_shapes_clip_and_report_changes() {
+        let mut map = Map::new(20, 20);
+
+        // Brushes are centred on the tile they're stamped at
+        let changed = draw::stamp(&mut map, Brush::square(1), 5, 5, TileType::Floor);
+        assert_eq!(changed.len(), 9);
+        assert!(changed.contains(&map.xy_idx(4, 4)) && changed.contains(&map.xy_idx(6, 6)));
+        assert!(draw::stamp(&mut map, Brush::square(1), 5, 5, TileType::Floor).is_empty());
+        assert_eq!(draw::stamp(&mut map, Brush::diamond(1), 12, 5, TileType::Floor).len(), 5);
+        assert_eq!(draw::fill_circle(&mut map, 12, 12, 2, TileType::Floor).len(), 21);
+
+        // Anything off the map is skipped
+        assert_eq!(draw::stamp(&mut map, Brush::square(1), 0, 0, TileType::Floor).len(), 4);
+        assert_eq!(draw::line(&mut map, -5, 18, 30, 18, TileType::Floor).len(), 20);
+
+        let mut map = Map::new(20, 20);
+        assert_eq!(draw::fill_rect(&mut map, 6, 6, 2, 2, TileType::Floor).len(), 25);
+        assert_eq!(draw::outline_rect(&mut map, 2, 2, 6, 6, TileType::Wall).len(), 16);
+        assert_eq!(draw::flood_fill(&mut map, 4, 4, TileType::Exit).len(), 9);
+
+        let changed = draw::corridor(&mut map, Position::new(2, 10), Position::new(10, 16), true, Brush::square(1), TileType::Floor);
+        assert_eq!(changed.len(), 3 * 11 + 3 * 6);
+        assert_eq!(map.get_tile(11, 9), TileType::Floor);
+        assert_eq!(map.get_tile(9, 17), TileType::Floor);
+    }
+
+    #[test]
+    fn symmetry_images() {
+        assert_eq!(Symmetry::Both.images(10, 8, 2, 1), vec![
+            Position::new(2, 1), Position::new(7, 1), Position::new(2, 6), Position::new(7, 6)
+        ]);
+        assert_eq!(Symmetry::Rotational2.images(10, 8, 2, 1), vec![Position::new(2, 1), Position::new(7, 6)]);
+        assert_eq!(Symmetry::Rotational4.images(9, 9, 1, 2), vec![
+            Position::new(1, 2), Position::new(6, 1), Position::new(7, 6), Position::new(2, 7)
+        ]);
+
+        // The centre of an odd sized map is its own image
+        assert_eq!(Symmetry::Both.images(9, 9, 4, 4), vec![Position::new(4, 4)]);
+        assert_eq!(Symmetry::Radial(6).images(41, 41, 20, 5).len(), 6);
+    }
+
+    #[test]
+    fn build_symmetric_arenas() {
+        let mut drunkard = DrunkardWalkMap::open_area(60, 60);
+        drunkard.settings.symmetry = Symmetry::Rotational4;
+        let mut dla = DlaMap::central_attractor(80, 50);
+        dla.settings.symmetry = Symmetry::Rotational2;
+
+        let mut architects: Vec<(Symmetry, Box<dyn Architect>)> = vec![
+            (Symmetry::Both, Box::new(MapSelector::drunkard_walk_fearful_symmetry(80, 50))),
+            (Symmetry::Both, Box::new(MapSelector::cellular_automata_arena_map(80, 50))),
+            (Symmetry::Rotational2, Box::new(CellularAutomataMap::new(80, 50).with_symmetry(Symmetry::Rotational2))),
+            (Symmetry::Rotational4, Box::new(drunkard)),
+            (Symmetry::Rotational2, Box::new(dla))
+        ];
+        for (symmetry, architect) in architects.iter_mut() {
+            for seed in 0..3 {
+                architect.build_with_seed(seed);
+                assert!(symmetry.is_symmetric(architect.get_map()), "{:?} map with seed {} isn't symmetric", symmetry, seed);
+                assert!(architect.get_map().validate().is_valid());
+            }
+        }
+
+        #[cfg(feature = "debug")]
+        output_map(architects[1].1.get_map(), "cellular_automata_arena_map.txt");
+    }
+
     #[test]
     fn build_maze_map() {
         let mut m = MapSelector::maze_map(80, 40);
@@ -97,6 +452,46 @@ mod tests {
         output_map(&m.map, "maze_map.txt");
     }
 
+    #[test]
+    fn build_maze_map_algorithms() {
+        type MazeBuilder = fn(i32, i32) -> MazeMap;
+        let builders: [(MazeBuilder, &str); 8] = [
+            (MapSelector::maze_map_prim, "maze_map_prim.txt"),
+            (MapSelector::maze_map_kruskal, "maze_map_kruskal.txt"),
+            (MapSelector::maze_map_eller, "maze_map_eller.txt"),
+            (MapSelector::maze_map_wilson, "maze_map_wilson.txt"),
+            (MapSelector::maze_map_aldous_broder, "maze_map_aldous_broder.txt"),
+            (MapSelector::maze_map_hunt_and_kill, "maze_map_hunt_and_kill.txt"),
+            (MapSelector::maze_map_binary_tree, "maze_map_binary_tree.txt"),
+            (MapSelector::maze_map_sidewinder, "maze_map_sidewinder.txt"),
+        ];
+
+        for (builder, name) in builders.iter() {
+            let mut m = builder(80, 40);
+            m.build();
+
+            // A perfect maze over 38x18 cells opens every cell plus exactly one fewer passage
+            let open = m.map.count_tile_type(TileType::Floor) + m.map.count_tile_type(TileType::Exit);
+            assert_eq!(open, 2 * 38 * 18 - 1, "{}", name);
+
+            #[cfg(feature = "debug")]
+            output_map(&m.map, name);
+        }
+    }
+
+    #[test]
+    fn build_maze_map_braided() {
+        let mut m = MapSelector::maze_map_braided(80, 40);
+        m.build();
+
+        // Braiding only ever adds passages, creating loops
+        let open = m.map.count_tile_type(TileType::Floor) + m.map.count_tile_type(TileType::Exit);
+        assert!(open > 2 * 38 * 18 - 1);
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "maze_map_braided.txt");
+    }
+
     #[test]
     fn build_dla_map_walk_inwards() {
         let mut m = MapSelector::dla_map_walk_inwards(100, 100);
@@ -133,6 +528,45 @@ mod tests {
         output_map(&m.map, "dla_map_insectoid.txt")
     }
 
+    #[test]
+    fn build_dla_map_coral() {
+        let mut m = MapSelector::dla_map_coral(80, 50);
+        m.build();
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "dla_map_coral.txt")
+    }
+
+    #[test]
+    fn dla_grows_from_several_seeds_within_its_limits() {
+        let mut m = DlaMap::walk_inwards(80, 50);
+        m.settings.seeds = vec![Position::new(15, 25), Position::new(65, 25)];
+        m.settings.bias = 0.2;
+        m.settings.stickiness = 0.5;
+        m.build_with_seed(6);
+        assert_eq!(m.map.start_position, Position::new(15, 25));
+        assert_eq!(m.map.get_tile(65, 25), TileType::Floor);
+        assert!(m.map.validate().is_valid());
+
+        // A capped build stops early rather than walking forever
+        let mut m = DlaMap::walk_inwards(80, 50);
+        m.settings.max_walkers = 50;
+        m.settings.max_walker_steps = 100;
+        m.build_with_seed(6);
+        assert!(m.map.count_tile_type(TileType::Floor) < 60);
+
+        let mut m = DlaMap::central_attractor(80, 50);
+        m.settings.algorithm = DlaAlgorithm::WalkOutwards;
+        m.settings.seeds = vec![Position::new(15, 25), Position::new(65, 25)];
+        m.build_with_seed(6);
+        assert!(m.map.validate().is_valid());
+
+        m.settings.algorithm = DlaAlgorithm::CentralAttractor;
+        m.settings.attractors = vec![Attractor::Line(Position::new(10, 40), Position::new(70, 40))];
+        m.build_with_seed(6);
+        assert!(m.map.validate().is_valid());
+    }
+
     #[test]
     fn build_voronoi_cell_map_pythagoras() {
         let mut m = MapSelector::voronoi_cell_map_pythagoras(100, 100);
@@ -151,6 +585,220 @@ mod tests {
         output_map(&m.map, "voronoi_cell_map_manhattan.txt")
     }
 
+    #[test]
+    fn build_voronoi_cell_map_chebyshev() {
+        let mut m = MapSelector::voronoi_cell_map_chebyshev(100, 100);
+        m.build();
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "voronoi_cell_map_chebyshev.txt")
+    }
+
+    #[test]
+    fn voronoi_seed_placements() {
+        let settings = |placement| VoronoiSettings {
+            n_seeds: 30,
+            distance_algorithm: DistanceAlgorithm::Pythagoras,
+            placement,
+            wall_thickness: 2
+        };
+
+        let mut m = VoronoiCellMap::with_settings(80, 50, settings(SeedPlacement::PoissonDisc { min_distance: 8.0 }));
+        m.build_with_seed(4);
+        assert!(!m.seeds.is_empty() && m.seeds.len() <= 30);
+        for (i, a) in m.seeds.iter().enumerate() {
+            for b in m.seeds[i + 1..].iter() {
+                assert!((a.x - b.x).pow(2) + (a.y - b.y).pow(2) >= 64);
+            }
+        }
+
+        let mut m = VoronoiCellMap::with_settings(80, 50, settings(SeedPlacement::JitteredGrid { jitter: 0.0 }));
+        m.build_with_seed(4);
+        assert!(m.seeds.len() >= 25 && m.seeds.len() <= 35, "{} seeds", m.seeds.len());
+
+        let points = vec![Position::new(20, 25), Position::new(60, 25)];
+        let mut m = VoronoiCellMap::with_settings(80, 50, settings(SeedPlacement::Custom(points.clone())));
+        m.build_with_seed(4);
+        assert_eq!(m.seeds, points);
+        assert!(m.map.validate().is_valid());
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "voronoi_cell_map_custom_seeds.txt");
+    }
+
+    #[test]
+    fn voronoi_regions_cover_every_walkable_tile() {
+        let mut rng = RandomNumberGenerator::seeded(9);
+        let mut m = MapSelector::dla_map_walk_inwards(80, 50);
+        m.build_with_rng(&mut rng);
+
+        let regions = voronoi_regions(&m.map, 8, DistanceAlgorithm::Pythagoras, &mut rng);
+        assert_eq!(regions.len(), 8);
+        assert!(regions.iter().all(|region| !region.is_empty()));
+
+        let mut tiles: Vec<usize> = regions.concat();
+        tiles.sort_unstable();
+        let walkable: Vec<usize> = (0..m.map.tiles.len()).filter(|idx| m.map.tiles[*idx] != TileType::Wall).collect();
+        assert_eq!(tiles, walkable);
+
+        assert!(voronoi_regions(&Map::new(10, 10), 4, DistanceAlgorithm::Manhattan, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn voronoi_membership_matches_brute_force() {
+        let algorithms = [DistanceAlgorithm::Pythagoras, DistanceAlgorithm::Manhattan, DistanceAlgorithm::Chebyshev];
+
+        for rng_seed in 0..5 {
+            let mut rng = RandomNumberGenerator::seeded(rng_seed);
+            for map in [Map::new(97, 61), Map::new_hex(97, 61)] {
+                let seeds: Vec<Position> = (0..40)
+                    .map(|_| Position::new(rng.range(0, map.width), rng.range(0, map.height)))
+                    .collect();
+
+                for algorithm in algorithms {
+                    let membership = voronoi_membership(&map, &seeds, algorithm);
+
+                    for (idx, member) in membership.iter().enumerate() {
+                        let tile = Position::new(idx as i32 % map.width, idx as i32 / map.width);
+                        let distance = |p: &Position| {
+                            let (dx, dy) = ((p.x - tile.x).abs(), (p.y - tile.y).abs());
+                            match algorithm {
+                                _ if map.grid == GridType::Hex => hex_distance(*p, tile),
+                                DistanceAlgorithm::Pythagoras => dx * dx + dy * dy,
+                                DistanceAlgorithm::Manhattan => dx + dy,
+                                DistanceAlgorithm::Chebyshev => dx.max(dy)
+                            }
+                        };
+                        let nearest = seeds.iter().map(distance).min().unwrap();
+                        let expected = seeds.iter().position(|p| distance(p) == nearest).unwrap();
+                        assert_eq!(*member, expected, "rng seed {}, {:?}", rng_seed, algorithm);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn build_large_voronoi_cell_map() {
+        let mut m = MapSelector::voronoi_cell_map_pythagoras(500, 500);
+        m.build();
+
+        assert_eq!(m.membership.len(), 500 * 500);
+    }
+
+    #[test]
+    fn seeded_builds_are_deterministic() {
+        let builders: Vec<fn() -> Box<dyn Architect>> = vec![
+            || Box::new(MapSelector::basic_map(60, 60)),
+            || Box::new(MapSelector::bsp_map(60, 60)),
+            || Box::new(MapSelector::bsp_interior_map(60, 60)),
+            || Box::new(MapSelector::cellular_automata_map(60, 60)),
+            || Box::new(MapSelector::drunkard_walk_fearful_symmetry(60, 60)),
+            || Box::new(MapSelector::maze_map_wilson(60, 60)),
+            || Box::new(MapSelector::dla_map_insectoid(60, 60)),
+            || Box::new(MapSelector::voronoi_cell_map_manhattan(60, 60)),
+        ];
+
+        for builder in builders.iter() {
+            let mut first = builder();
+            let mut second = builder();
+            first.build_with_seed(1234);
+            second.build_with_seed(1234);
+            assert_eq!(first.get_map(), second.get_map());
+        }
+
+        // The numbers behind a seed are fixed, so seeded maps don't change between releases
+        let mut rng = RandomNumberGenerator::seeded(1234);
+        let rolls: Vec<i32> = (0..6).map(|_| rng.roll_dice(1, 6)).collect();
+        assert_eq!((rolls, rng.next_u64()), (vec![5, 3, 1, 6, 4, 6], 11385644925188068500));
+    }
+
+    #[test]
+    fn rebuilding_with_a_seed_matches_a_fresh_build() {
+        for name in MapSelector::names() {
+            let mut fresh = MapSelector::by_name(name, 50, 40).unwrap();
+            fresh.get_map_mut().record_history();
+            fresh.build_with_seed(21);
+
+            // History recording carries over, but only the last build's snapshots are kept
+            let mut reused = MapSelector::by_name(name, 50, 40).unwrap();
+            reused.get_map_mut().record_history();
+            reused.build_with_seed(3);
+            reused.build_with_seed(21);
+            assert!(reused.get_map() == fresh.get_map(), "{} differs when rebuilt", name);
+        }
+    }
+
+    #[test]
+    fn generators_build_valid_maps_at_their_min_size() {
+        for name in MapSelector::names() {
+            let size = MapSelector::min_size(name).unwrap();
+            assert!(size <= MapSelector::MIN_SIZE, "{} needs more than MIN_SIZE", name);
+            for (width, height) in [(size, size), (size, 50), (50, size)] {
+                for seed in 0..5 {
+                    let mut m = MapSelector::by_name(name, width, height).unwrap();
+                    m.build_with_seed(seed);
+                    let report = m.get_map().validate();
+                    assert!(report.is_valid(), "{} at {}x{}, seed {}: {:?}", name, width, height, seed, report.problems);
+                }
+            }
+        }
+        assert_eq!(MapSelector::GENERATORS.iter().map(|g| g.min_size).max(), Some(MapSelector::MIN_SIZE));
+        assert_eq!(MapSelector::min_size("no_such_map"), None);
+
+        // A cave too small to leave any floor still has somewhere to start and finish
+        let mut m = MapSelector::cellular_automata_map(10, 10);
+        m.build_with_seed(0);
+        assert!(m.map.validate().is_valid());
+    }
+
+    #[test]
+    fn generate_batch_matches_individual_builds() {
+        let seeds: Vec<u64> = (0..16).collect();
+        let completed = AtomicUsize::new(0);
+
+        let maps = generate_batch_with_progress(
+            || MapSelector::drunkard_walk_map_winding_passages(50, 50),
+            &seeds,
+            |_, total| {
+                assert_eq!(total, 16);
+                completed.fetch_add(1, Ordering::Relaxed);
+            }
+        );
+
+        assert_eq!(completed.load(Ordering::Relaxed), 16);
+        for (seed, map) in seeds.iter().zip(maps.iter()) {
+            let mut m = MapSelector::drunkard_walk_map_winding_passages(50, 50);
+            m.build_with_seed(*seed);
+            assert_eq!(&m.map, map);
+        }
+        assert_eq!(maps, generate_batch(|| MapSelector::drunkard_walk_map_winding_passages(50, 50), &seeds));
+    }
+
+    #[test]
+    fn map_selector_by_name() {
+        for name in MapSelector::names() {
+            let mut m = MapSelector::by_name(name, 60, 40).unwrap();
+            m.build_with_seed(7);
+        }
+
+        assert!(MapSelector::by_name("no_such_map", 60, 40).is_none());
+    }
+
+    #[test]
+    fn map_stats() {
+        let mut map = Map::new(10, 10);
+        for x in 1..9 { map.set_tile(x, 2, TileType::Floor); }
+        map.set_tile(8, 2, TileType::Exit);
+        map.set_tile(5, 6, TileType::Floor);
+        map.start_position = Position::new(1, 2);
+
+        let stats = MapStats::from_map(&map);
+        assert_eq!(stats.floor_ratio, 0.09);
+        assert_eq!(stats.region_count, 2);
+        assert_eq!(stats.start_exit_distance, Some(7.0));
+    }
+
     #[test]
     fn build_random_map() {
         let mut m = MapSelector::random_map(100, 100);
@@ -159,4 +807,380 @@ mod tests {
         #[cfg(feature = "debug")]
         output_map(m.get_map(), "random_map.txt");
     }
+
+    #[test]
+    fn random_selector_weights_filters_and_replays() {
+        // "70% caves, 30% BSP", with nothing else ever picked
+        let selector = RandomSelector::new()
+            .with_generator("cellular_automata_map", 70).unwrap()
+            .with_generator("bsp_map", 30).unwrap()
+            .with_generator("maze_map", 0).unwrap();
+        let mut rng = RandomNumberGenerator::seeded(12);
+        let picks: Vec<RandomChoice> = (0..1000).map(|_| selector.choose(60, 40, &mut rng).unwrap()).collect();
+        let caves = picks.iter().filter(|c| c.name == "cellular_automata_map").count();
+        assert!(picks.iter().all(|c| c.name != "maze_map"));
+        assert!((630..=770).contains(&caves), "{} caves", caves);
+
+        // The reported name and seed build the same map again
+        let (choice, architect) = selector.generate(60, 40, &mut rng).unwrap();
+        assert_eq!(choice.build(60, 40).unwrap().get_map().tiles, architect.get_map().tiles);
+        let mut again = MapSelector::by_name(&choice.name, 60, 40).unwrap();
+        again.build_with_seed(choice.seed);
+        assert_eq!(again.get_map().tiles, architect.get_map().tiles);
+
+        // Every generator has its own name, so lookups find the right one
+        for (i, generator) in MapSelector::GENERATORS.iter().enumerate() {
+            assert_eq!(MapSelector::GENERATORS.iter().position(|g| g.name == generator.name), Some(i), "{} is listed twice", generator.name);
+        }
+        assert_eq!(MapSelector::tags("no_such_map"), None);
+
+        // Unknown names are refused, and never picked if they're slipped in by hand
+        assert!(RandomEntry::new("no_such_map", 1).is_none());
+        assert!(RandomSelector::new().with_generator("no_such_map", 1).is_none());
+        assert!(RandomChoice { name: "no_such_map".to_string(), seed: 1 }.build(60, 40).is_none());
+        let unknown = RandomEntry { name: "no_such_map".to_string(), weight: 1, tags: vec![], min_size: 0 };
+        assert_eq!(RandomSelector::new().with_entry(unknown.clone()).choose(60, 40, &mut rng), None);
+        let mut m = RandomMap::with_selector(60, 40, RandomSelector::new().with_entry(unknown));
+        m.build_with_seed(3);
+        assert_eq!(m.choice.unwrap().name, "basic_map");
+
+        // Tags and sizes narrow the choice down
+        let all = RandomSelector::all();
+        assert_eq!(all.entries.len(), MapSelector::GENERATORS.len() - 1);
+        assert!(all.tagged(Tag::Maze).entries.iter().all(|e| e.name.contains("maze")));
+        assert!(all.tagged(Tag::Symmetric).entries.iter().any(|e| e.name == "cellular_automata_arena_map"));
+        assert!(all.without(Tag::Hex).entries.iter().all(|e| !e.name.starts_with("hex_")));
+        assert!(all.tagged(Tag::Rooms).entries.iter().any(|e| e.name == "cave_and_fortress_map"));
+        assert!(all.fitting(15, 15).entries.iter().all(|e| e.name != "cave_and_fortress_map"));
+        assert_eq!(all.choose(5, 5, &mut rng), None);
+
+        // random_map picks from its build seed, and says what it picked
+        let mut m = MapSelector::random_map(60, 40);
+        m.build_with_seed(5);
+        let choice = m.choice.clone().unwrap();
+        assert_eq!(choice.build(60, 40).unwrap().get_map().tiles, m.map.tiles);
+        let mut again = MapSelector::random_map(60, 40);
+        again.build_with_seed(5);
+        assert_eq!(again.choice, Some(choice));
+        assert_eq!(again.map, m.map);
+    }
+
+    #[test]
+    fn hex_grid_geometry() {
+        // Odd-r offset round trips through axial coordinates
+        for y in 0..6 {
+            for x in 0..6 {
+                assert_eq!(Hex::from_offset(x, y).to_offset(), (x, y));
+            }
+        }
+
+        let mut map = Map::new_hex(10, 10);
+        for t in map.tiles.iter_mut() { *t = TileType::Floor; }
+
+        // Every interior hex has six neighbours, all one step away
+        let centre = Position::new(4, 5);
+        let neighbours = map.adjacent(centre.x, centre.y);
+        assert_eq!(neighbours.len(), 6);
+        for n in neighbours.iter() {
+            assert_eq!(map.distance(centre, *n), 1.0);
+        }
+
+        let idx = map.xy_idx(centre.x, centre.y);
+        assert_eq!(map.get_available_exits(idx).len(), 6);
+        assert_eq!(map.distance(Position::new(0, 0), Position::new(3, 3)), 5.0);
+
+        // A wall blocks sight to the tile directly behind it
+        map.set_tile(5, 5, TileType::Wall);
+        let visible = map.field_of_view(centre, 4);
+        assert!(visible.contains(&Position::new(5, 5)));
+        assert!(!visible.contains(&Position::new(6, 5)));
+        assert!(visible.contains(&Position::new(3, 5)));
+    }
+
+    #[test]
+    fn build_hex_cellular_automata_map() {
+        let mut m = MapSelector::hex_cellular_automata_map(80, 60);
+        m.build();
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "hex_cellular_automata_map.txt");
+    }
+
+    #[test]
+    fn build_hex_drunkard_walk_map() {
+        let mut m = MapSelector::hex_drunkard_walk_map_open_area(80, 60);
+        m.build();
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "hex_drunkard_walk_map.txt");
+    }
+
+    #[test]
+    fn build_hex_dla_map() {
+        let mut m = MapSelector::hex_dla_map_walk_inwards(80, 60);
+        m.build();
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "hex_dla_map.txt");
+    }
+
+    #[test]
+    fn build_hex_voronoi_cell_map() {
+        let mut m = MapSelector::hex_voronoi_cell_map(80, 60);
+        m.build();
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "hex_voronoi_cell_map.txt");
+    }
+
+    #[test]
+    fn recorded_history_ends_with_the_finished_map() {
+        for name in MapSelector::names() {
+            let mut m = MapSelector::by_name(name, 60, 40).unwrap();
+            m.get_map_mut().record_history();
+            m.build_with_seed(99);
+
+            let map = m.get_map();
+            let history = map.history.as_ref().unwrap();
+            assert!(history.len() > 1, "{} recorded {} snapshots", name, history.len());
+            assert_eq!(history.last(), Some(&map.tiles));
+        }
+    }
+
+    #[test]
+    fn history_is_not_recorded_by_default() {
+        let mut m = MapSelector::basic_map(60, 40);
+        m.build();
+        assert!(m.map.history.is_none());
+    }
+
+    #[test]
+    fn room_generators_record_their_rooms() {
+        let mut m = MapSelector::basic_map(80, 50);
+        m.build();
+        assert!(!m.map.rooms.is_empty());
+
+        let mut m = MapSelector::bsp_map(80, 50);
+        m.build();
+        assert_eq!(m.map.rooms, m.rooms);
+
+        let mut m = MapSelector::cellular_automata_map(80, 50);
+        m.build();
+        assert!(m.map.rooms.is_empty());
+    }
+
+    #[test]
+    fn build_from_custom_settings() {
+        let mut settings = MapSelector::drunkard_walk_fat_passages(80, 50).settings;
+        settings.floor_percent = 0.3;
+        let mut m = DrunkardWalkMap::with_settings(80, 50, settings.clone());
+        m.build();
+        assert_eq!(m.settings, settings);
+
+        let mut settings = MapSelector::dla_map_insectoid(80, 50).settings;
+        settings.symmetry = Symmetry::Vertical;
+        let mut m = DlaMap::with_settings(80, 50, settings);
+        m.build();
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "dla_map_vertical_insectoid.txt");
+    }
+
+    #[cfg(feature = "bevy")]
+    #[test]
+    fn bevy_plugin_spawns_and_regenerates_maps() {
+        use super::bevy_plugin::*;
+        use bevy::prelude::*;
+
+        let mut app = App::new();
+        app.add_plugins(CartographerPlugin {
+            config: MapConfig { generator: "bsp_map".to_string(), width: 60, height: 40, seed: Some(3), ..default() }
+        });
+        app.update();
+
+        let generated = app.world().resource::<GeneratedMap>().clone();
+        assert_eq!(generated.seed, 3);
+        assert!(generated.exit.is_some());
+
+        let world = app.world_mut();
+        assert_eq!(world.query::<&MapTile>().iter(world).count(), 60 * 40);
+        assert_eq!(world.query::<&MapRoom>().iter(world).count(), generated.rooms().len());
+        let start = world.query_filtered::<&MapTile, With<MapStart>>().single(world);
+        assert_eq!(start.position, generated.start());
+        let exit = world.query_filtered::<&MapTile, With<MapExit>>().single(world);
+        assert_eq!(Some(exit.position), generated.exit);
+
+        app.world_mut().send_event(GenerateMap { seed: Some(4) });
+        app.update();
+
+        let world = app.world_mut();
+        assert_eq!(world.resource::<GeneratedMap>().seed, 4);
+        assert_eq!(world.query::<&MapRoot>().iter(world).count(), 1);
+        assert_eq!(world.query::<&MapTile>().iter(world).count(), 60 * 40);
+    }
+
+    #[test]
+    fn generated_maps_pass_validation() {
+        for name in MapSelector::names() {
+            for seed in 0..5 {
+                let mut m = MapSelector::by_name(name, 80, 50).unwrap();
+                m.build_with_seed(seed);
+                let report = m.get_map().validate();
+                assert!(report.is_valid(), "{} with seed {}: {:?}", name, seed, report.problems);
+            }
+        }
+    }
+
+    #[test]
+    fn validation_reports_broken_maps() {
+        let mut map = Map::new(10, 10);
+        map.start_position = Position::new(0, 0);
+        map.set_tile(0, 5, TileType::Floor);
+        map.set_tile(3, 3, TileType::Floor);
+        map.set_tile(6, 6, TileType::Exit);
+        map.set_tile(8, 2, TileType::Void);
+        map.rooms.push(Room::new(5, 5, 6, 3));
+
+        let problems = map.validate().problems;
+        assert!(problems.contains(&MapProblem::StartNotWalkable(Position::new(0, 0))));
+        assert!(problems.contains(&MapProblem::UnreachableExit(Position::new(6, 6))));
+        assert!(problems.contains(&MapProblem::OpenBorder(vec![Position::new(0, 5)])));
+        assert!(problems.contains(&MapProblem::RoomOutOfBounds(Room::new(5, 5, 6, 3))));
+        assert!(problems.contains(&MapProblem::IsolatedPocket(vec![map.xy_idx(3, 3)])));
+        assert!(problems.contains(&MapProblem::StrayVoid(vec![Position::new(8, 2)])));
+        assert!(!problems.contains(&MapProblem::NoExit));
+    }
+
+    #[test]
+    fn transforms_round_trip() {
+        let mut m = MapSelector::bsp_map(60, 40);
+        m.build_with_seed(5);
+        let map = m.map.clone();
+
+        let quarter = map.rotated(Rotation::Quarter).unwrap();
+        assert_eq!((quarter.width, quarter.height), (40, 60));
+        let start = map.start_position;
+        assert_eq!(quarter.start_position, Position::new(39 - start.y, start.x));
+        assert_eq!(quarter.get_tile(39 - start.y, start.x), map.get_tile(start.x, start.y));
+
+        let full_turn = quarter.rotated(Rotation::Quarter).unwrap().rotated(Rotation::Half).unwrap();
+        assert_eq!(full_turn, map);
+        assert_eq!(map.rotated(Rotation::ThreeQuarters).unwrap().rotated(Rotation::Quarter).unwrap(), map);
+        assert_eq!(map.flipped(Flip::Horizontal).unwrap().flipped(Flip::Horizontal).unwrap(), map);
+        assert_eq!(map.flipped(Flip::Vertical).unwrap().flipped(Flip::Horizontal), map.rotated(Rotation::Half));
+
+        assert!(map.rotated(Rotation::Quarter).unwrap().validate().is_valid());
+        assert!(map.flipped(Flip::Vertical).unwrap().validate().is_valid());
+
+        // Rooms stay on their floors, which run from x1 + 1 to x2
+        let mut basic = MapSelector::basic_map(60, 40);
+        basic.build_with_seed(5);
+        for source in [&map, &basic.map] {
+            let transformed = [
+                source.rotated(Rotation::Quarter), source.rotated(Rotation::Half), source.rotated(Rotation::ThreeQuarters),
+                source.flipped(Flip::Horizontal), source.flipped(Flip::Vertical)
+            ];
+            for t in transformed.iter().map(|t| t.as_ref().unwrap()) {
+                assert_eq!(t.rooms.len(), source.rooms.len());
+                for room in t.rooms.iter() {
+                    for y in room.y1 + 1 ..= room.y2 {
+                        for x in room.x1 + 1 ..= room.x2 {
+                            assert_ne!(t.get_tile(x, y), TileType::Wall, "wall at ({}, {}) in {:?}", x, y, room);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Hex rows are offset, so they can't be turned or mirrored
+        let hex = Map::new_hex(20, 20);
+        assert!(hex.rotated(Rotation::Half).is_none());
+        assert!(hex.flipped(Flip::Horizontal).is_none());
+
+        let padded = map.padded(3, 2, 1, 4, TileType::Wall);
+        assert_eq!((padded.width, padded.height), (64, 46));
+        assert_eq!(padded.cropped(3, 2, 60, 40), map);
+        assert_eq!(map.resized(70, 50, TileType::Wall).resized(60, 40, TileType::Wall), map);
+
+        // Negative sizes come out empty rather than panicking, and negative padding trims
+        let empty = map.cropped(5, 5, -3, 10);
+        assert_eq!((empty.width, empty.height, empty.tiles.len()), (0, 10, 0));
+        assert_eq!(map.padded(-100, 0, 0, 0, TileType::Wall).tiles.len(), 0);
+        assert_eq!(map.padded(-3, -2, -1, -4, TileType::Wall), map.cropped(3, 2, 56, 34));
+        assert_eq!(map.resized(-1, -1, TileType::Wall).tiles.len(), 0);
+
+        // History is in the old shape, so only the recording carries over
+        let mut recording = map.clone();
+        recording.record_history();
+        recording.take_snapshot();
+        assert_eq!(recording.padded(1, 1, 1, 1, TileType::Wall).history, Some(Vec::new()));
+        assert_eq!(map.rotated(Rotation::Quarter).unwrap().history, None);
+    }
+
+    #[test]
+    fn blit_a_maze_vault_into_a_bsp_map() {
+        let mut bsp = MapSelector::bsp_map(80, 50);
+        bsp.build_with_seed(11);
+        let mut vault = MapSelector::maze_map(21, 21);
+        vault.build_with_seed(11);
+
+        // Drop the vault's own exit and outer wall so it opens into whatever it lands on
+        let mut vault = vault.map.cropped(1, 1, 19, 19);
+        for tile in vault.tiles.iter_mut() {
+            if *tile == TileType::Exit { *tile = TileType::Floor; }
+        }
+
+        let mut map = bsp.map.clone();
+        map.blit(&vault, 70, 40);
+        for y in 0..50 {
+            for x in 0..80 {
+                let expected = if x >= 70 && y >= 40 { vault.get_tile(x - 70, y - 40) } else { bsp.map.get_tile(x, y) };
+                assert_eq!(map.get_tile(x, y), expected);
+            }
+        }
+        assert_eq!(map.rooms, bsp.map.rooms);
+        assert_eq!(map.start_position, bsp.map.start_position);
+    }
+
+    #[test]
+    fn build_cave_and_fortress_map() {
+        let mut m = MapSelector::cave_and_fortress_map(80, 50);
+        m.build();
+        assert!(m.map.validate().is_valid());
+        assert!(!m.map.rooms.is_empty());
+
+        #[cfg(feature = "debug")]
+        output_map(&m.map, "cave_and_fortress_map.txt");
+    }
+
+    #[test]
+    fn build_composite_maps_from_each_partition() {
+        let mask: Vec<usize> = (0..80 * 50).map(|idx| if (idx % 80) < 20 || (idx / 80) < 15 { 1 } else { 0 }).collect();
+        let partitions = vec![
+            Partition::Bsp { regions: 4 },
+            Partition::Voronoi { regions: 5 },
+            Partition::Mask(mask.clone())
+        ];
+
+        for partition in partitions {
+            let mut m = CompositeMap::new(80, 50, partition, MapSelector::maze_map).unwrap()
+                .with_region(MapSelector::drunkard_walk_map_open_halls)
+                .with_region(MapSelector::dla_map_central_attractor);
+            m.build_with_seed(8);
+
+            let report = m.map.validate();
+            assert!(report.is_valid(), "{:?}", report.problems);
+            assert_eq!(m.regions.len(), 80 * 50);
+        }
+
+        // A mask the wrong size is refused up front rather than panicking in build
+        assert!(CompositeMap::new(80, 50, Partition::Mask(vec![0; 10]), MapSelector::maze_map).is_none());
+
+        // Region ids needn't be dense
+        let sparse: Vec<usize> = mask.iter().map(|r| if *r == 1 { usize::MAX } else { 1_000_000 }).collect();
+        let mut m = CompositeMap::new(80, 50, Partition::Mask(sparse), MapSelector::maze_map).unwrap()
+            .with_region(MapSelector::dla_map_central_attractor);
+        m.build_with_seed(8);
+        assert!(m.map.validate().is_valid());
+    }
 }
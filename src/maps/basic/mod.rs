@@ -1,48 +1,106 @@
-use std::cmp::{min, max};
-
+use super::draw::{self, Brush};
 use super::utils::*;
 
 use super::Architect;
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BasicSettings {
+    /// Stop once this many rooms are placed; `None` places as many as fit
+    pub max_rooms: Option<usize>,
+    /// Tries at placing a room, including those which overlap another and are thrown away;
+    /// `None` is one for every hundred tiles of the map
+    pub placement_attempts: Option<usize>,
+    /// Floor sizes of the rooms, inclusive
+    pub min_width: i32,
+    pub max_width: i32,
+    pub min_height: i32,
+    pub max_height: i32,
+    /// Extra wall tiles kept between rooms, on top of the single one they always have
+    pub spacing: i32,
+    /// Walls over the edge of the map once everything is dug. Rooms are placed inside it
+    /// anyway, so this can be turned off to save the pass.
+    pub fill_edges: bool
+}
+
+impl Default for BasicSettings {
+    fn default() -> Self {
+        Self {
+            max_rooms: None,
+            placement_attempts: None,
+            min_width: 6,
+            max_width: 9,
+            min_height: 6,
+            max_height: 9,
+            spacing: 0,
+            fill_edges: true
+        }
+    }
+}
+
 pub struct BasicMap {
     pub map: Map,
     pub width: i32,
-    pub height: i32
+    pub height: i32,
+    pub settings: BasicSettings,
+    /// Rooms from the last build, in the order they were joined up
+    pub rooms: Vec<Room>
 }
 
 impl BasicMap {
-    pub fn new(width: i32, height: i32) -> Self {
+    pub fn with_settings(width: i32, height: i32, settings: BasicSettings) -> Self {
         Self {
             map: Map::new(width, height),
             width,
-            height
+            height,
+            settings,
+            rooms: Vec::new()
         }
     }
 
-    fn apply_room_to_map(&mut self, room : &Room) {
-        for y in room.y1 + 1 ..= room.y2 {
-            for x in room.x1 + 1 ..= room.x2 {
-                self.map.set_tile(x, y, TileType::Floor);
-            }
-        }
+    pub fn new(width: i32, height: i32) -> Self {
+        Self::with_settings(width, height, BasicSettings::default())
     }
 
-    fn apply_horizontal_tunnel(&mut self, x1:i32, x2:i32, y:i32) {
-        for x in min(x1, x2) ..= max(x1, x2) {
-            let idx = self.map.xy_idx(x, y);
-            if idx > 0 && idx < (self.width * self.height) as usize {
-                self.map.set_tile(x, y, TileType::Floor);
-            }
-        }
+    /// A handful of big halls, well apart
+    pub fn halls(width: i32, height: i32) -> Self {
+        Self::with_settings(width, height, BasicSettings {
+            max_rooms: Some(8),
+            min_width: 8,
+            max_width: 16,
+            min_height: 6,
+            max_height: 10,
+            spacing: 3,
+            ..BasicSettings::default()
+        })
     }
 
-    fn apply_vertical_tunnel(&mut self, y1:i32, y2:i32, x:i32) {
-        for y in min(y1, y2) ..= max(y1, y2) {
-            let idx = self.map.xy_idx(x, y);
-            if idx > 0 && idx < (self.width * self.height) as usize {
-                self.map.set_tile(x, y, TileType::Floor);
-            }
-        }
+    /// Lots of small rooms packed closely together
+    pub fn cells(width: i32, height: i32) -> Self {
+        Self::with_settings(width, height, BasicSettings {
+            placement_attempts: Some((width * height / 40) as usize),
+            min_width: 3,
+            max_width: 5,
+            min_height: 3,
+            max_height: 5,
+            fill_edges: false,
+            ..BasicSettings::default()
+        })
+    }
+
+    /// A room at a random spot inside the border, if one that size fits at all
+    fn random_room(&self, rng: &mut RandomNumberGenerator) -> Option<Room> {
+        let s = &self.settings;
+        let w = rng.range(s.min_width, i32::max(s.min_width, s.max_width) + 1);
+        let h = rng.range(s.min_height, i32::max(s.min_height, s.max_height) + 1);
+        if w > self.width - 2 || h > self.height - 2 { return None; }
+
+        let x = rng.roll_dice(1, self.width - w - 1) - 1;
+        let y = rng.roll_dice(1, self.height - h - 1) - 1;
+        Some(Room::new(x, y, w, h))
+    }
+
+    fn apply_room_to_map(&mut self, room : &Room) {
+        draw::fill_rect(&mut self.map, room.x1 + 1, room.y1 + 1, room.x2, room.y2, TileType::Floor);
     }
 
     fn fill_edges(&mut self) {
@@ -57,42 +115,44 @@ impl BasicMap {
 }
 
 impl Architect for BasicMap {
-    fn build(&mut self) {
+    fn build_with_rng(&mut self, rng: &mut RandomNumberGenerator) {
+        self.map.clear();
         let mut rooms : Vec<Room> = Vec::new();
-        let max_rooms : f32 = (self.width * self.height) as f32 * 0.01;
-        let min_size : i32 = 6;
-        let max_size : i32 = 10;
-
-        let mut rng = RandomNumberGenerator::new();
-
-        for _ in 0..max_rooms as i32 {
-            let mut ok = true;
-            let w = rng.range(min_size, max_size);
-            let h = rng.range(min_size, max_size);
-            let x = rng.roll_dice(1, (self.width + 1) - w - 1) - 1;
-            let y = rng.roll_dice(1, (self.height + 1) - h - 1) - 1;
-
-            let new_room = Room::new(x, y, w, h);
-            for other_room in rooms.iter() {
-                if new_room.intersect(other_room) { ok = false }
-            }
-            if ok {
-                self.apply_room_to_map(&new_room);
-
-                if !rooms.is_empty() {
-                    let (new_x, new_y) = new_room.center();
-                    let (prev_x, prev_y) = rooms[rooms.len() - 1].center();
-                    if rng.range(0, 2) == 1 {
-                        self.apply_horizontal_tunnel(prev_x, new_x, prev_y);
-                        self.apply_vertical_tunnel(prev_y, new_y, new_x);
-                    } else {
-                        self.apply_vertical_tunnel(prev_y, new_y, prev_x);
-                        self.apply_horizontal_tunnel(prev_x, new_x, new_y);
-                    }
-                }
+        let attempts = self.settings.placement_attempts.unwrap_or((self.width * self.height) as usize / 100);
+        let max_rooms = self.settings.max_rooms.unwrap_or(usize::MAX);
+        let spacing = i32::max(self.settings.spacing, 0);
+
+        for _ in 0..attempts {
+            if rooms.len() >= max_rooms { break; }
+            let Some(new_room) = self.random_room(rng) else { continue };
+
+            let padded = Room { x1: new_room.x1 - spacing, y1: new_room.y1 - spacing, x2: new_room.x2 + spacing, y2: new_room.y2 + spacing };
+            if rooms.iter().any(|other_room| padded.intersect(other_room)) { continue; }
 
-                rooms.push(new_room);
+            self.apply_room_to_map(&new_room);
+
+            if !rooms.is_empty() {
+                let (new_x, new_y) = new_room.center();
+                let (prev_x, prev_y) = rooms[rooms.len() - 1].center();
+                let horizontal_first = rng.range(0, 2) == 1;
+                let (from, to) = (Position::new(prev_x, prev_y), Position::new(new_x, new_y));
+                draw::corridor(&mut self.map, from, to, horizontal_first, Brush::square(0), TileType::Floor);
             }
+
+            rooms.push(new_room);
+            self.map.take_snapshot();
+        }
+
+        // Without any room at all, fall back on a scrap of floor in the middle
+        if rooms.is_empty() {
+            let room = Room::new(self.width / 2 - 1, self.height / 2 - 1, 2, 2);
+            self.apply_room_to_map(&room);
+            rooms.push(room);
+        }
+
+        // Seal the edges before placing the exit, so the exit can never be walled over
+        if self.settings.fill_edges {
+            self.fill_edges();
         }
 
         let (start_x, start_y) = rooms[0].center();
@@ -100,14 +160,19 @@ impl Architect for BasicMap {
 
         let (exit_x, exit_y) = rooms[rooms.len() - 1].center();
         self.map.set_tile(exit_x, exit_y, TileType::Exit);
-
-        self.fill_edges();
+        self.map.rooms = rooms.clone();
+        self.rooms = rooms;
+        self.map.take_snapshot();
     }
 
     fn get_map(&self) -> &Map {
         &self.map
     }
 
+    fn get_map_mut(&mut self) -> &mut Map {
+        &mut self.map
+    }
+
     fn start_position(&self) -> (i32, i32) {
         (self.map.start_position.x, self.map.start_position.y)
     }
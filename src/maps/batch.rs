@@ -0,0 +1,57 @@
+//! Batch generation
+//!
+//! Builds one map per seed from a factory of architects. With the `rayon` feature enabled
+//! the maps are built in parallel. Every map is built from its own seeded generator, so the
+//! results are the same whatever the thread count, and come back in the order of `seeds`.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use super::{utils::Map, Architect};
+
+/// Builds a map for every seed using a fresh architect from `spec`.
+///
+/// ```
+/// use cartographer_rs::maps::{batch::generate_batch, MapSelector};
+///
+/// let maps = generate_batch(|| MapSelector::cellular_automata_map(40, 40), &[1, 2, 3]);
+/// assert_eq!(maps.len(), 3);
+/// ```
+pub fn generate_batch<A, F>(spec: F, seeds: &[u64]) -> Vec<Map>
+where
+    A: Architect,
+    F: Fn() -> A + Sync
+{
+    generate_batch_with_progress(spec, seeds, |_, _| {})
+}
+
+/// As `generate_batch`, calling `progress(completed, total)` each time a map is finished.
+/// With `rayon` the callback may be called from several threads at once, and maps can
+/// finish out of order.
+pub fn generate_batch_with_progress<A, F, P>(spec: F, seeds: &[u64], progress: P) -> Vec<Map>
+where
+    A: Architect,
+    F: Fn() -> A + Sync,
+    P: Fn(usize, usize) + Sync
+{
+    let completed = AtomicUsize::new(0);
+    let total = seeds.len();
+
+    let build = |seed: &u64| {
+        let mut architect = spec();
+        architect.build_with_seed(*seed);
+        let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
+        progress(done, total);
+        architect.get_map().clone()
+    };
+
+    #[cfg(feature = "rayon")]
+    let maps = seeds.par_iter().map(build).collect();
+
+    #[cfg(not(feature = "rayon"))]
+    let maps = seeds.iter().map(build).collect();
+
+    maps
+}
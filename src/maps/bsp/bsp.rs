@@ -1,146 +1,92 @@
-use crate::maps::{utils::*, Architect};
+use crate::maps::{draw::{self, Brush}, utils::*, Architect};
+use super::tree::{BspSettings, BspTree};
 
 pub struct BspMap {
     pub map: Map,
     pub width: i32,
     pub height: i32,
     pub rooms: Vec<Room>,
-    pub(crate) rects: Vec<Room>,
+    pub settings: BspSettings,
+    /// The partition from the last build, with one room somewhere inside each leaf
+    pub tree: BspTree
 }
 
 impl BspMap {
-    pub fn new(width: i32, height: i32) -> Self {
+    pub fn with_settings(width: i32, height: i32, settings: BspSettings) -> Self {
         Self {
             map: Map::new(width, height),
             width,
             height,
             rooms: Vec::new(),
-            rects: Vec::new()
+            settings,
+            tree: BspTree::default()
         }
     }
 
-    fn add_subrects(&mut self, rect: Room) {
-        let width = i32::abs((rect.x1 - rect.x2) as i32);
-        let height = i32::abs((rect.y1 - rect.y2) as i32);
-        let half_width = i32::max(width / 2, 1) as i32;
-        let half_height = i32::max(height / 2, 1) as i32;
-
-        self.rects.push(Room::new( rect.x1, rect.y1, half_width, half_height ));
-        self.rects.push(Room::new( rect.x1, rect.y1 + half_height, half_width, half_height ));
-        self.rects.push(Room::new( rect.x1 + half_width, rect.y1, half_width, half_height ));
-        self.rects.push(Room::new( rect.x1 + half_width, rect.y1 + half_height, half_width, half_height ));
-    }
-
-    fn get_random_rect(&mut self, rng : &mut RandomNumberGenerator) -> Room {
-        if self.rects.len() == 1 { return self.rects[0]; }
-        let idx = (rng.roll_dice(1, self.rects.len() as i32) - 1) as usize;
-        self.rects[idx]
-    }
-
-    fn get_random_sub_rect(&self, rect : Room, rng : &mut RandomNumberGenerator) -> Room {
-        let mut result = rect;
-        let rect_width = i32::abs((rect.x1 - rect.x2) as i32);
-        let rect_height = i32::abs((rect.y1 - rect.y2) as i32);
-    
-        let w = i32::max(3, rng.roll_dice(1, i32::min(rect_width, 10)) - 1) + 1;
-        let h = i32::max(3, rng.roll_dice(1, i32::min(rect_height, 10)) - 1) + 1;
-    
-        result.x1 += rng.roll_dice(1, 6)-1;
-        result.y1 += rng.roll_dice(1, 6)-1;
-        result.x2 = result.x1 + w;
-        result.y2 = result.y1 + h;
-    
-        result
+    pub fn new(width: i32, height: i32) -> Self {
+        Self::with_settings(width, height, BspSettings {
+            min_leaf_size: 8,
+            max_leaf_size: 16,
+            min_split_ratio: 0.35,
+            max_split_ratio: 0.65,
+            ..BspSettings::default()
+        })
     }
 
-    fn is_possible(&self, rect : Room) -> bool {
-        let mut expanded = rect;
-        expanded.x1 -= 2;
-        expanded.x2 += 2;
-        expanded.y1 -= 2;
-        expanded.y2 += 2;
-    
-        let mut can_build = true;
-    
-        for y in expanded.y1 ..= expanded.y2 {
-            for x in expanded.x1 ..= expanded.x2 {
-                if x > self.width-2 { can_build = false; }
-                if y > self.height-2 { can_build = false; }
-                if x < 1 { can_build = false; }
-                if y < 1 { can_build = false; }
-                if can_build {
-                    if self.map.get_tile(x, y) != TileType::Wall {
-                        can_build = false;
-                    }
-                }
-            }
-        }
-    
-        can_build
+    /// A room somewhere in `leaf`, a tile clear of its edges so rooms in neighbouring leaves
+    /// never touch. Room floors run from `x1 + 1` to `x2`.
+    fn room_in_leaf(&self, leaf: Room, rng: &mut RandomNumberGenerator) -> Option<Room> {
+        let max_w = i32::min(10, leaf.x2 - leaf.x1 - 1);
+        let max_h = i32::min(10, leaf.y2 - leaf.y1 - 1);
+        if max_w < 1 || max_h < 1 { return None; }
+
+        let w = rng.range(i32::min(3, max_w), max_w + 1);
+        let h = rng.range(i32::min(3, max_h), max_h + 1);
+        let x = rng.range(leaf.x1 + 1, leaf.x2 - w + 1);
+        let y = rng.range(leaf.y1 + 1, leaf.y2 - h + 1);
+        Some(Room { x1: x - 1, y1: y - 1, x2: x + w - 1, y2: y + h - 1 })
     }
 
     fn apply_room_to_map(&mut self, room : &Room) {
-        for y in room.y1 + 1 ..= room.y2 {
-            for x in room.x1 + 1 ..= room.x2 {
-                self.map.set_tile(x, y, TileType::Floor);
-            }
-        }
-    }
-
-    fn draw_corridor(&mut self, x1:i32, y1:i32, x2:i32, y2:i32) {
-        let mut x = x1;
-        let mut y = y1;
-    
-        while x != x2 || y != y2 {
-            if x < x2 {
-                x += 1;
-            } else if x > x2 {
-                x -= 1;
-            } else if y < y2 {
-                y += 1;
-            } else if y > y2 {
-                y -= 1;
-            }
-    
-            self.map.set_tile(x, y, TileType::Floor);
-        }
+        draw::fill_rect(&mut self.map, room.x1 + 1, room.y1 + 1, room.x2, room.y2, TileType::Floor);
     }
 }
 
 impl Architect for BspMap {
-    fn build(&mut self) {
-        let mut rng = RandomNumberGenerator::new();
-
-        self.rects.clear();
-        self.rects.push( Room::new(2, 2, self.width-5, self.height-5) ); // Start with a single map-sized rectangle
-        let first_room = self.rects[0];
-        self.add_subrects(first_room); // Divide the first room
-
-        // Up to 240 times, we get a random rectangle and divide it. If its possible to squeeze a
-        // room in there, we place it and add it to the rooms list.
-        let mut n_rooms = 0;
-        while n_rooms < 240 {
-            let rect = self.get_random_rect(&mut rng);
-            let candidate = self.get_random_sub_rect(rect, &mut rng);
-
-            if self.is_possible(candidate) {
-                self.apply_room_to_map(&candidate);
-                self.rooms.push(candidate);
-                self.add_subrects(rect);
+    fn build_with_rng(&mut self, rng: &mut RandomNumberGenerator) {
+        self.map.clear();
+        self.rooms.clear();
+        self.tree = BspTree::new(Room { x1: 1, y1: 1, x2: self.width - 2, y2: self.height - 2 }, &self.settings, rng);
+
+        // One room per leaf
+        for leaf in self.tree.leaves() {
+            if let Some(room) = self.room_in_leaf(self.tree.nodes[leaf].rect, rng) {
+                self.apply_room_to_map(&room);
+                self.rooms.push(room);
+                self.map.take_snapshot();
             }
+        }
 
-            n_rooms += 1;
+        // Without any room at all, fall back on a scrap of floor in the middle
+        if self.rooms.is_empty() {
+            let room = Room::new(self.width / 2 - 1, self.height / 2 - 1, 2, 2);
+            self.apply_room_to_map(&room);
+            self.rooms.push(room);
         }
 
         // Now we want corridors
-        for i in 0..self.rooms.len()-1 {
+        for i in 0..self.rooms.len() - 1 {
             let room = self.rooms[i];
             let next_room = self.rooms[i + 1];
-            let start_x = room.x1 + (rng.roll_dice(1, i32::abs(room.x1 - room.x2)) - 1);
-            let start_y = room.y1 + (rng.roll_dice(1, i32::abs(room.y1 - room.y2)) - 1);
-            let end_x = next_room.x1 + (rng.roll_dice(1, i32::abs(next_room.x1 - next_room.x2)) - 1);
-            let end_y = next_room.y1 + (rng.roll_dice(1, i32::abs(next_room.y1 - next_room.y2)) - 1);
-            self.draw_corridor(start_x, start_y, end_x, end_y);
+            // Room floors run from x1 + 1 to x2, so pick the ends from inside that or the
+            // corridor can set off along the room's wall and never join it
+            let start_x = room.x1 + rng.roll_dice(1, i32::abs(room.x1 - room.x2));
+            let start_y = room.y1 + rng.roll_dice(1, i32::abs(room.y1 - room.y2));
+            let end_x = next_room.x1 + rng.roll_dice(1, i32::abs(next_room.x1 - next_room.x2));
+            let end_y = next_room.y1 + rng.roll_dice(1, i32::abs(next_room.y1 - next_room.y2));
+            let (from, to) = (Position::new(start_x, start_y), Position::new(end_x, end_y));
+            draw::corridor(&mut self.map, from, to, true, Brush::square(0), TileType::Floor);
+            self.map.take_snapshot();
         }
 
         let (start_x, start_y) = self.rooms[0].center();
@@ -148,12 +94,18 @@ impl Architect for BspMap {
 
         let (exit_x, exit_y) = self.rooms[self.rooms.len() - 1].center();
         self.map.set_tile(exit_x, exit_y, TileType::Exit);
+        self.map.rooms = self.rooms.clone();
+        self.map.take_snapshot();
     }
 
     fn get_map(&self) -> &Map {
         &self.map
     }
 
+    fn get_map_mut(&mut self) -> &mut Map {
+        &mut self.map
+    }
+
     fn start_position(&self) -> (i32, i32) {
         (self.map.start_position.x, self.map.start_position.y)
     }
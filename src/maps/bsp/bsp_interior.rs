@@ -1,101 +1,48 @@
-use crate::maps::{utils::*, Architect};
+use crate::maps::{draw::{self, Brush}, utils::*, Architect};
+use super::tree::{BspSettings, BspTree};
 
 pub struct BspInteriorMap {
     pub map: Map,
     pub width: i32,
     pub height: i32,
     pub rooms: Vec<Room>,
-    pub(crate) rects: Vec<Room>
+    pub settings: BspSettings,
+    /// The partition from the last build; every leaf is a room
+    pub tree: BspTree
 }
 
 impl BspInteriorMap {
-    pub fn new(width: i32, height: i32) -> Self {
+    pub fn with_settings(width: i32, height: i32, settings: BspSettings) -> Self {
         Self {
             map: Map::new(width, height),
             width,
             height,
             rooms: Vec::new(),
-            rects: Vec::new()
+            settings,
+            tree: BspTree::default()
         }
     }
 
-    fn add_subrects(&mut self, rect : Room, rng : &mut RandomNumberGenerator) {
-        const MIN_ROOM_SIZE: i32 = 6;
-        
-        // Remove the last rect from the list
-        if !self.rects.is_empty() {
-            self.rects.remove(self.rects.len() - 1);
-        }
-    
-        // Calculate boundaries
-        let width  = rect.x2 - rect.x1;
-        let height = rect.y2 - rect.y1;
-        let half_width = width / 2;
-        let half_height = height / 2;
-    
-        let split = rng.roll_dice(1, 4);
-    
-        if split <= 2 {
-            // Horizontal split
-            let h1 = Room::new( rect.x1, rect.y1, half_width - 1, height );
-            self.rects.push( h1 );
-            if half_width > MIN_ROOM_SIZE { self.add_subrects(h1, rng); }
-            let h2 = Room::new( rect.x1 + half_width, rect.y1, half_width, height );
-            self.rects.push( h2 );
-            if half_width > MIN_ROOM_SIZE { self.add_subrects(h2, rng); }
-        } else {
-            // Vertical split
-            let v1 = Room::new( rect.x1, rect.y1, width, half_height - 1 );
-            self.rects.push(v1);
-            if half_height > MIN_ROOM_SIZE { self.add_subrects(v1, rng); }
-            let v2 = Room::new( rect.x1, rect.y1 + half_height, width, half_height );
-            self.rects.push(v2);
-            if half_height > MIN_ROOM_SIZE { self.add_subrects(v2, rng); }
-        }
-    }
-
-
-    fn draw_corridor(&mut self, x1:i32, y1:i32, x2:i32, y2:i32) {
-        let mut x = x1;
-        let mut y = y1;
-
-        while x != x2 || y != y2 {
-            if x < x2 {
-                x += 1;
-            } else if x > x2 {
-                x -= 1;
-            } else if y < y2 {
-                y += 1;
-            } else if y > y2 {
-                y -= 1;
-            }
-
-            self.map.set_tile(x, y, TileType::Floor);
-        }
+    pub fn new(width: i32, height: i32) -> Self {
+        Self::with_settings(width, height, BspSettings::default())
     }
 }
 
 impl Architect for BspInteriorMap {
-    fn build(&mut self) {
-        let mut rng = RandomNumberGenerator::new();
+    fn build_with_rng(&mut self, rng: &mut RandomNumberGenerator) {
+        self.map.clear();
+        self.rooms.clear();
+        // Each leaf keeps its last column and row as wall, so the partition takes in the right
+        // and bottom edges of the map. A leaf one tile across would be all wall, so none are
+        // cut narrower than two.
+        let settings = BspSettings { min_leaf_size: i32::max(2, self.settings.min_leaf_size), ..self.settings };
+        self.tree = BspTree::new(Room { x1: 1, y1: 1, x2: self.width - 1, y2: self.height - 1 }, &settings, rng);
 
-        self.rects.clear();
-        self.rects.push( Room::new(1, 1, self.width - 2, self.height -  2) ); // Start with a single map-sized rectangle
-        let first_room = self.rects[0];
-        self.add_subrects(first_room, &mut rng); // Divide the first room
-
-        let rooms = self.rects.clone();
-        for r in rooms.iter() {
-            let room = *r;
+        for leaf in self.tree.leaves() {
+            let room = self.tree.nodes[leaf].rect;
             self.rooms.push(room);
-            for y in room.y1 .. room.y2 {
-                for x in room.x1 .. room.x2 {
-                    let idx = self.map.xy_idx(x, y);
-                    if idx > 0 && idx < ((self.width * self.height)-1) as usize {
-                        self.map.set_tile(x, y, TileType::Floor);
-                    }
-                }
-            }
+            draw::fill_rect(&mut self.map, room.x1, room.y1, room.x2 - 1, room.y2 - 1, TileType::Floor);
+            self.map.take_snapshot();
         }
 
         // Corridors
@@ -106,7 +53,9 @@ impl Architect for BspInteriorMap {
             let start_y = room.y1 + (rng.roll_dice(1, i32::abs(room.y1 - room.y2)) - 1);
             let end_x = next_room.x1 + (rng.roll_dice(1, i32::abs(next_room.x1 - next_room.x2)) - 1);
             let end_y = next_room.y1 + (rng.roll_dice(1, i32::abs(next_room.y1 - next_room.y2)) - 1);
-            self.draw_corridor(start_x, start_y, end_x, end_y);
+            let (from, to) = (Position::new(start_x, start_y), Position::new(end_x, end_y));
+            draw::corridor(&mut self.map, from, to, true, Brush::square(0), TileType::Floor);
+            self.map.take_snapshot();
         }
 
         let (start_x, start_y) = self.rooms[0].center();
@@ -114,12 +63,18 @@ impl Architect for BspInteriorMap {
 
         let (exit_x, exit_y) = self.rooms[self.rooms.len() - 1].center();
         self.map.set_tile(exit_x, exit_y, TileType::Exit);
+        self.map.rooms = self.rooms.clone();
+        self.map.take_snapshot();
     }
 
     fn get_map(&self) -> &Map {
         &self.map
     }
 
+    fn get_map_mut(&mut self) -> &mut Map {
+        &mut self.map
+    }
+
     fn start_position(&self) -> (i32, i32) {
         (self.map.start_position.x, self.map.start_position.y)
     }
@@ -1,2 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod bsp;
-pub mod bsp_interior;
\ No newline at end of file
+pub mod bsp_interior;
+pub mod tree;
@@ -0,0 +1,141 @@
+//! Binary space partitioning
+//!
+//! A rectangle is cut in two, and each half cut again, until the pieces are small enough.
+//! The whole tree is kept, so callers can join siblings up, give each subtree its own zone,
+//! or draw the partition.
+
+use crate::maps::utils::{RandomNumberGenerator, Room};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BspSettings {
+    /// Neither side of a leaf is ever cut below this
+    pub min_leaf_size: i32,
+    /// Leaves keep being cut until both sides are at most this, or `max_depth` is reached
+    pub max_leaf_size: i32,
+    /// Where along its side a rect is cut, picked between these fractions
+    pub min_split_ratio: f32,
+    pub max_split_ratio: f32,
+    /// Rects this many times longer one way than the other are always cut across their
+    /// longer side; squarer ones are cut either way
+    pub aspect_bias: f32,
+    /// Number of cuts between the root and the deepest leaf
+    pub max_depth: u32
+}
+
+impl Default for BspSettings {
+    fn default() -> Self {
+        Self {
+            min_leaf_size: 6,
+            max_leaf_size: 12,
+            min_split_ratio: 0.4,
+            max_split_ratio: 0.6,
+            aspect_bias: 1.5,
+            max_depth: 16
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BspNode {
+    /// The tiles this node covers, `x1..=x2` by `y1..=y2`
+    pub rect: Room,
+    pub depth: u32,
+    pub parent: Option<usize>,
+    /// The left or top half, then the right or bottom half. `None` for a leaf.
+    pub children: Option<(usize, usize)>
+}
+
+/// Nodes indexed by position in `nodes`, with the root first
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BspTree {
+    pub nodes: Vec<BspNode>
+}
+
+impl BspTree {
+    /// Partitions `rect`, whose corners are both included
+    pub fn new(rect: Room, settings: &BspSettings, rng: &mut RandomNumberGenerator) -> Self {
+        let mut tree = Self { nodes: vec![BspNode { rect, depth: 0, parent: None, children: None }] };
+
+        let mut open = vec![0];
+        while let Some(idx) = open.pop() {
+            let node = tree.nodes[idx];
+            let Some((first, second)) = split(node.rect, node.depth, settings, rng) else { continue };
+
+            let child = |rect| BspNode { rect, depth: node.depth + 1, parent: Some(idx), children: None };
+            tree.nodes.push(child(first));
+            tree.nodes.push(child(second));
+            let children = (tree.nodes.len() - 2, tree.nodes.len() - 1);
+            tree.nodes[idx].children = Some(children);
+            open.push(children.1);
+            open.push(children.0);
+        }
+        tree
+    }
+
+    pub fn root(&self) -> usize {
+        0
+    }
+
+    pub fn is_leaf(&self, node: usize) -> bool {
+        self.nodes[node].children.is_none()
+    }
+
+    /// Every leaf, left or top halves first, so neighbouring leaves in the list are close
+    /// together on the map
+    pub fn leaves(&self) -> Vec<usize> {
+        if self.nodes.is_empty() { return Vec::new(); }
+        self.leaves_under(self.root())
+    }
+
+    /// The leaves of the subtree below `node`, in the same order as `leaves`
+    pub fn leaves_under(&self, node: usize) -> Vec<usize> {
+        let mut leaves = Vec::new();
+        let mut open = vec![node];
+        while let Some(idx) = open.pop() {
+            match self.nodes[idx].children {
+                Some((first, second)) => {
+                    open.push(second);
+                    open.push(first);
+                },
+                None => leaves.push(idx)
+            }
+        }
+        leaves
+    }
+
+    /// The two halves of every cut, from the root down
+    pub fn siblings(&self) -> Vec<(usize, usize)> {
+        self.nodes.iter().filter_map(|node| node.children).collect()
+    }
+}
+
+/// Cuts `rect` in two, or returns `None` if it should be a leaf
+fn split(rect: Room, depth: u32, settings: &BspSettings, rng: &mut RandomNumberGenerator) -> Option<(Room, Room)> {
+    let (width, height) = (rect.x2 - rect.x1 + 1, rect.y2 - rect.y1 + 1);
+    if depth >= settings.max_depth { return None; }
+    if width <= settings.max_leaf_size && height <= settings.max_leaf_size { return None; }
+
+    let min = i32::max(settings.min_leaf_size, 1);
+    let (can_cut_width, can_cut_height) = (width >= 2 * min, height >= 2 * min);
+    let across_width = match (can_cut_width, can_cut_height) {
+        (false, false) => return None,
+        (true, false) => true,
+        (false, true) => false,
+        _ if width as f32 >= height as f32 * settings.aspect_bias => true,
+        _ if height as f32 >= width as f32 * settings.aspect_bias => false,
+        _ => rng.roll_dice(1, 2) == 1
+    };
+
+    let side = if across_width { width } else { height };
+    let (low, high) = (settings.min_split_ratio.min(settings.max_split_ratio), settings.min_split_ratio.max(settings.max_split_ratio));
+    let ratio = low + (high - low) * rng.range(0, 1001) as f32 / 1000.0;
+    let first_size = ((side as f32 * ratio).round() as i32).clamp(min, side - min);
+
+    Some(if across_width {
+        let split = rect.x1 + first_size;
+        (Room { x2: split - 1, ..rect }, Room { x1: split, ..rect })
+    } else {
+        let split = rect.y1 + first_size;
+        (Room { y2: split - 1, ..rect }, Room { y1: split, ..rect })
+    })
+}
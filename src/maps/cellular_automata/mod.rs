@@ -1,81 +1,222 @@
+pub mod rules;
+
 use crate::maps::{utils::*, Architect};
+use rules::{step, Neighbourhood, OutOfBounds, Phase, Rule};
+
+/// How the map is seeded before the rules run
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InitialFill {
+    /// Every tile has the same chance of starting as wall
+    Uniform { wall_percent: i32 },
+    /// Random values on a grid `scale` tiles apart, blended in between, with wall wherever the
+    /// blend falls under `wall_percent`. Gives blobs rather than speckle.
+    Noise { wall_percent: i32, scale: i32 },
+    /// Like `Uniform`, but the chance rises towards `edge_percent` within `width` tiles of the
+    /// edge, so caves keep away from the border
+    BorderBias { wall_percent: i32, edge_percent: i32, width: i32 }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellularAutomataSettings {
+    pub fill: InitialFill,
+    /// Run in order, each for its own number of iterations
+    pub phases: Vec<Phase>,
+    pub out_of_bounds: OutOfBounds
+}
 
 pub struct CellularAutomataMap {
     pub map: Map,
     pub width: i32,
-    pub height: i32
+    pub height: i32,
+    pub settings: CellularAutomataSettings,
+    /// Symmetry of the starting noise. The rules treat every neighbour alike, so the caves
+    /// keep whatever symmetry the noise starts with.
+    pub symmetry: Symmetry
 }
 
 impl CellularAutomataMap {
-    pub fn new(width: i32, height: i32) -> Self {
+    pub fn with_settings(width: i32, height: i32, settings: CellularAutomataSettings) -> Self {
         Self {
             map: Map::new(width, height),
             width,
-            height
+            height,
+            settings,
+            symmetry: Symmetry::None
         }
     }
-}
 
-impl Architect for CellularAutomataMap {
-    fn build(&mut self) {
-        let mut rng = RandomNumberGenerator::new();
+    /// Winding caves broken up by pillars
+    pub fn new(width: i32, height: i32) -> Self {
+        Self::with_settings(width, height, Self::square_caves())
+    }
+
+    /// A few large chambers with smooth walls
+    pub fn cavernous(width: i32, height: i32) -> Self {
+        Self::with_settings(width, height, CellularAutomataSettings {
+            fill: InitialFill::BorderBias { wall_percent: 45, edge_percent: 80, width: 3 },
+            phases: vec![
+                Phase::new(Rule::new(5..=8, 4..=8), Neighbourhood::Moore(1), 5),
+                Phase::new(Rule::new(13..=24, 12..=24), Neighbourhood::Moore(2), 2)
+            ],
+            out_of_bounds: OutOfBounds::Wall
+        })
+    }
+
+    /// Mostly floor, with scattered rock
+    pub fn open_caves(width: i32, height: i32) -> Self {
+        Self::with_settings(width, height, CellularAutomataSettings {
+            fill: InitialFill::Noise { wall_percent: 40, scale: 6 },
+            phases: vec![
+                Phase::new(Rule::new(6..=8, 3..=8), Neighbourhood::Moore(1), 6)
+            ],
+            out_of_bounds: OutOfBounds::Wall
+        })
+    }
+
+    /// Narrow twisting tunnels
+    pub fn tight_caves(width: i32, height: i32) -> Self {
+        Self::with_settings(width, height, CellularAutomataSettings {
+            fill: InitialFill::Uniform { wall_percent: 50 },
+            phases: vec![
+                Phase::new(Rule::new(5..=8, 4..=8), Neighbourhood::Moore(1), 4),
+                Phase::new(Rule::new(3..=4, 1..=4), Neighbourhood::VonNeumann(1), 2)
+            ],
+            out_of_bounds: OutOfBounds::Wall
+        })
+    }
+
+    /// Walled off caves mirrored into all four corners
+    pub fn arena(width: i32, height: i32) -> Self {
+        Self::new(width, height).with_symmetry(Symmetry::Both)
+    }
+
+    pub fn with_symmetry(mut self, symmetry: Symmetry) -> Self {
+        self.symmetry = symmetry;
+        self
+    }
+
+    /// Builds on a hex grid instead of a square one. The default square rules are swapped
+    /// for their hex equivalent; custom settings are kept as they are.
+    pub fn on_hex_grid(mut self) -> Self {
+        self.map = Map::new_hex(self.width, self.height);
+        if self.settings == Self::square_caves() {
+            self.settings = Self::hex_caves();
+        }
+        self
+    }
+
+    /// Wall with more than four wall neighbours, or none at all to break up open areas
+    fn square_caves() -> CellularAutomataSettings {
+        CellularAutomataSettings {
+            fill: InitialFill::Uniform { wall_percent: 55 },
+            phases: vec![Phase::new(Rule::new([0, 5, 6, 7, 8], [0, 5, 6, 7, 8]), Neighbourhood::Moore(1), 15)],
+            out_of_bounds: OutOfBounds::Wall
+        }
+    }
+
+    /// A hex tile only has six neighbours, so open areas are too small to need breaking up
+    /// with pillars; instead a wall survives with three wall neighbours. Hex rules keep walls
+    /// more readily, so they start from an even split.
+    fn hex_caves() -> CellularAutomataSettings {
+        CellularAutomataSettings {
+            fill: InitialFill::Uniform { wall_percent: 50 },
+            phases: vec![Phase::new(Rule::new(4..=6, 3..=6), Neighbourhood::Moore(1), 15)],
+            out_of_bounds: OutOfBounds::Wall
+        }
+    }
+
+    /// Randomizes everything inside the border
+    fn fill(&mut self, rng: &mut RandomNumberGenerator) {
+        // Noise is blended from a coarse grid of random values, rolled up front
+        let scale = match self.settings.fill {
+            InitialFill::Noise { scale, .. } => i32::max(scale, 1),
+            _ => 1
+        };
+        let lattice_w = self.width / scale + 2;
+        let lattice: Vec<f32> = match self.settings.fill {
+            InitialFill::Noise { .. } => (0..lattice_w * (self.height / scale + 2)).map(|_| rng.range(0, 100) as f32).collect(),
+            _ => Vec::new()
+        };
+        let at = |lx: i32, ly: i32| lattice[(ly * lattice_w + lx) as usize];
 
-        // First we completely randomize the map, setting 55% of it to be floor.
         for y in 1..self.height - 1 {
             for x in 1..self.width - 1 {
-                let roll = rng.roll_dice(1, 100);
-                if roll > 55 { self.map.set_tile(x, y, TileType::Floor) } 
-                else { self.map.set_tile(x, y, TileType::Wall) }
+                let wall = match self.settings.fill {
+                    InitialFill::Uniform { wall_percent } => rng.roll_dice(1, 100) <= wall_percent,
+                    InitialFill::Noise { wall_percent, .. } => {
+                        let (lx, ly) = (x / scale, y / scale);
+                        let (fx, fy) = ((x % scale) as f32 / scale as f32, (y % scale) as f32 / scale as f32);
+                        let top = at(lx, ly) * (1.0 - fx) + at(lx + 1, ly) * fx;
+                        let bottom = at(lx, ly + 1) * (1.0 - fx) + at(lx + 1, ly + 1) * fx;
+                        top * (1.0 - fy) + bottom * fy < wall_percent as f32
+                    },
+                    InitialFill::BorderBias { wall_percent, edge_percent, width } => {
+                        let from_edge = i32::min(i32::min(x, self.width - 1 - x), i32::min(y, self.height - 1 - y));
+                        let chance = if from_edge >= width {
+                            wall_percent
+                        } else {
+                            edge_percent + (wall_percent - edge_percent) * from_edge / width
+                        };
+                        rng.roll_dice(1, 100) <= chance
+                    }
+                };
+                self.map.set_tile(x, y, if wall { TileType::Wall } else { TileType::Floor });
             }
         }
+    }
+}
 
-        // Now we iteratively apply cellular automata rules
-        for _i in 0..15 {
-            let mut newtiles = self.map.tiles.clone();
+impl Architect for CellularAutomataMap {
+    fn build_with_rng(&mut self, rng: &mut RandomNumberGenerator) {
+        self.map.clear();
+        self.fill(rng);
 
+        // Give each set of matching tiles the value of the first of them
+        if self.symmetry != Symmetry::None {
+            let noise = self.map.tiles.clone();
             for y in 1..self.height - 1 {
                 for x in 1..self.width - 1 {
-                    let idx = self.map.xy_idx(x, y);
-                    let mut neighbors = 0;
-                    if self.map.get_tile_at_idx(idx - 1) == TileType::Wall { neighbors += 1; }
-                    if self.map.get_tile_at_idx(idx + 1) == TileType::Wall { neighbors += 1; }
-                    if self.map.get_tile_at_idx(idx - self.width as usize) == TileType::Wall { neighbors += 1; }
-                    if self.map.get_tile_at_idx(idx + self.width as usize) == TileType::Wall { neighbors += 1; }
-                    if self.map.get_tile_at_idx(idx - (self.width as usize - 1)) == TileType::Wall { neighbors += 1; }
-                    if self.map.get_tile_at_idx(idx - (self.width as usize + 1)) == TileType::Wall { neighbors += 1; }
-                    if self.map.get_tile_at_idx(idx + (self.width as usize - 1)) == TileType::Wall { neighbors += 1; }
-                    if self.map.get_tile_at_idx(idx + (self.width as usize + 1)) == TileType::Wall { neighbors += 1; }
-
-                    if neighbors > 4 || neighbors == 0 {
-                        newtiles[idx] = TileType::Wall;
-                    }
-                    else {
-                        newtiles[idx] = TileType::Floor;
-                    }
+                    let first = self.symmetry.images(self.width, self.height, x, y)
+                        .iter()
+                        .map(|p| self.map.xy_idx(p.x, p.y))
+                        .min()
+                        .unwrap();
+                    self.map.set_tile(x, y, noise[first]);
                 }
             }
-
-            self.map.tiles = newtiles.clone();
         }
+        self.map.take_snapshot();
 
-        // Find a starting point
-        let mut start = Position::new(self.width / 2, self.height / 2);
-        while self.map.get_tile(start.x, start.y) != TileType::Floor {
-            start.x -= 1;
+        // Now we iteratively apply cellular automata rules
+        for phase in self.settings.phases.iter() {
+            for _i in 0..phase.iterations {
+                step(&mut self.map, &phase.rule, phase.neighbourhood, self.settings.out_of_bounds);
+                self.map.take_snapshot();
+            }
         }
 
+        // Start in the biggest cave, carving a tile if the rules left no floor at all
+        let start = central_start(&self.map);
+        self.map.set_tile(start.x, start.y, TileType::Floor);
+
         let (start_x, start_y) = (start.x, start.y);
         let start_idx = self.map.xy_idx(start_x, start_y);
         self.map.start_position = start;
 
+        connect_images(&mut self.map, self.symmetry, start);
         let exit_idx = remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx);
         self.map.set_tile_at_idx(exit_idx, TileType::Exit);
+        self.map.take_snapshot();
     }
 
     fn get_map(&self) -> &Map {
         &self.map
     }
 
+    fn get_map_mut(&mut self) -> &mut Map {
+        &mut self.map
+    }
+
     fn start_position(&self) -> (i32, i32) {
         (self.map.start_position.x, self.map.start_position.y)
     }
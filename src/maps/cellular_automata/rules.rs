@@ -0,0 +1,201 @@
+//! Cellular automata rules
+//!
+//! Walls are the live cells. Each step counts the walls around every tile and looks the count
+//! up in a `Rule`, written in the usual B/S notation: `B5678/S45678` turns floor into wall
+//! with five or more wall neighbours, and keeps a wall with four or more. `smooth` runs the
+//! same rules over any finished map.
+
+use std::fmt;
+
+use crate::maps::draw::Brush;
+use crate::maps::utils::{GridType, Map, TileType};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    /// Wall counts which turn a floor tile into wall
+    pub birth: Vec<u32>,
+    /// Wall counts which keep a wall tile as wall
+    pub survival: Vec<u32>
+}
+
+impl Rule {
+    pub fn new<B, S>(birth: B, survival: S) -> Self
+    where
+        B: IntoIterator<Item = u32>,
+        S: IntoIterator<Item = u32>
+    {
+        Self { birth: birth.into_iter().collect(), survival: survival.into_iter().collect() }
+    }
+
+    /// Reads B/S notation such as `B678/S2345678`, in either order and either case. Counts
+    /// above 9 only turn up with larger neighbourhoods; write those with commas, e.g.
+    /// `B13,14,15/S12,13,14,15`.
+    pub fn parse(notation: &str) -> Option<Self> {
+        let mut birth = None;
+        let mut survival = None;
+
+        for part in notation.split('/') {
+            let part = part.trim();
+            let (slot, counts) = match part.chars().next()?.to_ascii_uppercase() {
+                'B' => (&mut birth, &part[1..]),
+                'S' => (&mut survival, &part[1..]),
+                _ => return None
+            };
+            if slot.is_some() { return None; }
+
+            let counts: Option<Vec<u32>> = if counts.contains(',') {
+                counts.split(',').map(|c| c.trim().parse().ok()).collect()
+            } else {
+                counts.chars().map(|c| c.to_digit(10)).collect()
+            };
+            *slot = Some(counts?);
+        }
+
+        Some(Self { birth: birth?, survival: survival? })
+    }
+
+    /// Whether a tile ends up as wall, given whether it is one now and its wall count
+    pub fn next(&self, wall: bool, walls: u32) -> bool {
+        if wall { self.survival.contains(&walls) } else { self.birth.contains(&walls) }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts = |counts: &[u32]| {
+            let separator = if counts.iter().any(|c| *c > 9) { "," } else { "" };
+            counts.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(separator)
+        };
+        write!(f, "B{}/S{}", counts(&self.birth), counts(&self.survival))
+    }
+}
+
+/// Which tiles count as neighbours, out to the given radius. Hex grids count every tile
+/// within the radius either way.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// The surrounding square, diagonals included
+    Moore(i32),
+    /// The surrounding diamond, so only orthogonal steps at radius 1
+    VonNeumann(i32)
+}
+
+impl Neighbourhood {
+    fn brush(self) -> Brush {
+        match self {
+            Neighbourhood::Moore(radius) => Brush::square(radius),
+            Neighbourhood::VonNeumann(radius) => Brush::diamond(radius)
+        }
+    }
+}
+
+/// What neighbours past the edge of the map count as
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OutOfBounds {
+    Wall,
+    Floor,
+    /// The map wraps round, so the far edge is the neighbour
+    Wrap
+}
+
+/// A rule run a number of times over the map
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phase {
+    pub rule: Rule,
+    pub neighbourhood: Neighbourhood,
+    pub iterations: u32
+}
+
+impl Phase {
+    pub fn new(rule: Rule, neighbourhood: Neighbourhood, iterations: u32) -> Self {
+        Self { rule, neighbourhood, iterations }
+    }
+
+    /// The classic `B5678/S45678` cave rule, which rounds corners off and fills in specks
+    pub fn smoothing(iterations: u32) -> Self {
+        Self::new(Rule::new(5..=8, 4..=8), Neighbourhood::Moore(1), iterations)
+    }
+}
+
+/// Number of walls around `(x, y)`, not counting the tile itself
+pub fn count_walls(map: &Map, neighbourhood: Neighbourhood, out_of_bounds: OutOfBounds, x: i32, y: i32) -> u32 {
+    let mut walls = 0;
+    for p in neighbourhood.brush().footprint(map.grid, x, y) {
+        if p.x == x && p.y == y { continue; }
+
+        let wall = if map.in_bounds(p.x, p.y) {
+            map.get_tile(p.x, p.y) == TileType::Wall
+        } else {
+            match out_of_bounds {
+                OutOfBounds::Wall => true,
+                OutOfBounds::Floor => false,
+                OutOfBounds::Wrap => {
+                    // Odd-r rows only line up again after an even number of rows, so hex maps
+                    // with an odd height just wrap sideways
+                    let wrap_y = if map.grid == GridType::Hex && map.height % 2 == 1 { p.y } else { p.y.rem_euclid(map.height) };
+                    map.in_bounds(p.x.rem_euclid(map.width), wrap_y)
+                        && map.get_tile(p.x.rem_euclid(map.width), wrap_y) == TileType::Wall
+                }
+            }
+        };
+        if wall { walls += 1; }
+    }
+    walls
+}
+
+/// Applies `rule` once to every tile inside the border, returning the tiles which changed.
+/// The border itself is left alone.
+pub fn step(map: &mut Map, rule: &Rule, neighbourhood: Neighbourhood, out_of_bounds: OutOfBounds) -> Vec<usize> {
+    step_where(map, rule, neighbourhood, out_of_bounds, |_| true)
+}
+
+fn step_where<F>(map: &mut Map, rule: &Rule, neighbourhood: Neighbourhood, out_of_bounds: OutOfBounds, can_change: F) -> Vec<usize>
+where
+    F: Fn(usize) -> bool
+{
+    let mut newtiles = map.tiles.clone();
+    let mut changed = Vec::new();
+
+    for y in 1..map.height - 1 {
+        for x in 1..map.width - 1 {
+            let idx = map.xy_idx(x, y);
+            if !can_change(idx) { continue; }
+
+            let wall = map.tiles[idx] == TileType::Wall;
+            let walls = count_walls(map, neighbourhood, out_of_bounds, x, y);
+            let next = rule.next(wall, walls);
+            if next != wall {
+                newtiles[idx] = if next { TileType::Wall } else { TileType::Floor };
+                changed.push(idx);
+            }
+        }
+    }
+
+    map.tiles = newtiles;
+    changed
+}
+
+/// Runs `phase` over a finished map from any generator, e.g. to roughen room walls or erode
+/// a maze into ruins. Only floor and wall tiles change, and only where `mask` is true; exits,
+/// `Void`, the start and the border are left as they are. Returns the tiles which ended up
+/// different. Walls can grow across passages, so check `validate` if connectivity matters.
+pub fn smooth(map: &mut Map, phase: &Phase, out_of_bounds: OutOfBounds, mask: Option<&[bool]>) -> Vec<usize> {
+    if let Some(mask) = mask {
+        assert_eq!(mask.len(), map.tiles.len(), "a smoothing mask needs one entry per tile");
+    }
+
+    let before = map.tiles.clone();
+    let start = map.start_position;
+    let start_idx = map.in_bounds(start.x, start.y).then(|| map.xy_idx(start.x, start.y));
+    let can_change = |idx: usize| {
+        matches!(before[idx], TileType::Floor | TileType::Wall)
+            && Some(idx) != start_idx
+            && mask.is_none_or(|mask| mask[idx])
+    };
+
+    for _i in 0..phase.iterations {
+        step_where(map, &phase.rule, phase.neighbourhood, out_of_bounds, can_change);
+    }
+
+    (0..map.tiles.len()).filter(|idx| map.tiles[*idx] != before[*idx]).collect()
+}
@@ -0,0 +1,230 @@
+//! Composite maps
+//!
+//! Splits the map into regions and fills each one with a different architect, e.g. caves on
+//! one side and rooms on the other. Each region's architect builds a map the size of the
+//! region's bounding box, and only the tiles inside the region are kept. Region borders are
+//! walled off, and any areas left disconnected are then joined to the start with corridors,
+//! so every floor tile can be reached.
+
+use std::collections::BTreeMap;
+
+use super::utils::*;
+use super::voronoi_cell::{voronoi_membership, DistanceAlgorithm};
+use super::Architect;
+
+/// Regions narrower than this are built at this size and cropped, as generators need room
+/// for their own borders
+const MIN_REGION_SIZE: i32 = 10;
+
+/// Makes the architect for a region, given the size of the region's bounding box
+pub type ArchitectFactory = Box<dyn Fn(i32, i32) -> Box<dyn Architect> + Send + Sync>;
+
+/// How the map is divided up
+pub enum Partition {
+    /// Repeatedly cuts the largest area in two across its longer side
+    Bsp { regions: usize },
+    /// Voronoi cells around randomly placed seeds
+    Voronoi { regions: usize },
+    /// The region of every tile, row by row
+    Mask(Vec<usize>)
+}
+
+pub struct CompositeMap {
+    pub map: Map,
+    pub width: i32,
+    pub height: i32,
+    partition: Partition,
+    /// One per region, reused in order if there are more regions than architects. Never empty.
+    architects: Vec<ArchitectFactory>,
+    /// Region of every tile after building
+    pub regions: Vec<usize>
+}
+
+impl CompositeMap {
+    /// Starts a composite map with the architect for its first region, which also holds the
+    /// start. Returns `None` if a `Partition::Mask` doesn't have exactly one region per tile.
+    pub fn new<A, F>(width: i32, height: i32, partition: Partition, first_region: F) -> Option<Self>
+    where
+        A: Architect + 'static,
+        F: Fn(i32, i32) -> A + Send + Sync + 'static
+    {
+        if let Partition::Mask(mask) = &partition {
+            if mask.len() != (i32::max(0, width) * i32::max(0, height)) as usize { return None; }
+        }
+        Some(Self::with_partition(width, height, partition).with_region(first_region))
+    }
+
+    fn with_partition(width: i32, height: i32, partition: Partition) -> Self {
+        Self {
+            map: Map::new(width, height),
+            width,
+            height,
+            partition,
+            architects: Vec::new(),
+            regions: Vec::new()
+        }
+    }
+
+    /// Adds the architect for the next region
+    pub fn with_region<A, F>(mut self, factory: F) -> Self
+    where
+        A: Architect + 'static,
+        F: Fn(i32, i32) -> A + Send + Sync + 'static
+    {
+        self.architects.push(Box::new(move |w, h| Box::new(factory(w, h))));
+        self
+    }
+
+    /// Caves on one side and the rooms of a ruined fortress on the other
+    pub fn cave_and_fortress(width: i32, height: i32) -> Self {
+        Self::with_partition(width, height, Partition::Bsp { regions: 2 })
+            .with_region(super::cellular_automata::CellularAutomataMap::new)
+            .with_region(super::bsp::bsp_interior::BspInteriorMap::new)
+    }
+
+    fn partition(&self, rng: &mut RandomNumberGenerator) -> Vec<usize> {
+        match &self.partition {
+            Partition::Bsp { regions } => {
+                let mut rects = vec![Room::new(0, 0, self.width - 1, self.height - 1)];
+                while rects.len() < *regions {
+                    // Split the biggest area left, so the regions stay roughly even
+                    let (i, rect) = rects.iter().copied().enumerate()
+                        .max_by_key(|(_, r)| (r.x2 - r.x1) * (r.y2 - r.y1))
+                        .unwrap();
+                    let (w, h) = (rect.x2 - rect.x1, rect.y2 - rect.y1);
+                    if w < 2 && h < 2 { break; }
+
+                    let (first, second) = if w >= h {
+                        let split = rect.x1 + w * rng.range(40, 61) / 100;
+                        (Room { x2: split, ..rect }, Room { x1: split + 1, ..rect })
+                    } else {
+                        let split = rect.y1 + h * rng.range(40, 61) / 100;
+                        (Room { y2: split, ..rect }, Room { y1: split + 1, ..rect })
+                    };
+                    rects[i] = first;
+                    rects.insert(i + 1, second);
+                }
+
+                let mut membership = vec![0; self.map.tiles.len()];
+                for (region, rect) in rects.iter().enumerate() {
+                    for y in rect.y1 ..= rect.y2 {
+                        for x in rect.x1 ..= rect.x2 {
+                            membership[self.map.xy_idx(x, y)] = region;
+                        }
+                    }
+                }
+                membership
+            },
+            Partition::Voronoi { regions } => {
+                let n_seeds = usize::min(*regions, self.map.tiles.len());
+                let mut seeds: Vec<Position> = Vec::with_capacity(n_seeds);
+                while seeds.len() < n_seeds {
+                    let seed = Position::new(rng.roll_dice(1, self.width) - 1, rng.roll_dice(1, self.height) - 1);
+                    if !seeds.contains(&seed) { seeds.push(seed); }
+                }
+                voronoi_membership(&self.map, &seeds, DistanceAlgorithm::Pythagoras)
+            },
+            Partition::Mask(mask) => mask.clone()
+        }
+    }
+}
+
+impl Architect for CompositeMap {
+    fn build_with_rng(&mut self, rng: &mut RandomNumberGenerator) {
+        self.map.clear();
+        self.regions = self.partition(rng);
+
+        // Tiles of each region in one pass, so sparse region ids cost nothing extra
+        let mut by_region: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (idx, region) in self.regions.iter().enumerate() {
+            by_region.entry(*region).or_default().push(idx);
+        }
+
+        let mut start = None;
+        for (region, tiles) in by_region {
+            // Bounding box of the region
+            let xs = tiles.iter().map(|idx| *idx as i32 % self.width);
+            let ys = tiles.iter().map(|idx| *idx as i32 / self.width);
+            let (x1, x2) = (xs.clone().min().unwrap(), xs.max().unwrap());
+            let (y1, y2) = (ys.clone().min().unwrap(), ys.max().unwrap());
+            let w = i32::max(MIN_REGION_SIZE, x2 - x1 + 1);
+            let h = i32::max(MIN_REGION_SIZE, y2 - y1 + 1);
+
+            let mut architect = (self.architects[region % self.architects.len()])(w, h);
+            architect.build_with_rng(rng);
+            let sub = architect.get_map();
+
+            for idx in tiles.iter() {
+                let (x, y) = (*idx as i32 % self.width, *idx as i32 / self.width);
+                self.map.tiles[*idx] = match sub.get_tile(x - x1, y - y1) {
+                    // Each region brings its own exit; a single one is picked at the end
+                    TileType::Exit => TileType::Floor,
+                    tile => tile
+                };
+            }
+
+            for room in sub.rooms.iter() {
+                let room = Room { x1: room.x1 + x1, y1: room.y1 + y1, x2: room.x2 + x1, y2: room.y2 + y1 };
+                let (cx, cy) = room.center();
+                if self.map.in_bounds(cx, cy) && self.regions[self.map.xy_idx(cx, cy)] == region {
+                    self.map.rooms.push(room);
+                }
+            }
+
+            if start.is_none() {
+                let (sx, sy) = architect.start_position();
+                let (sx, sy) = (sx + x1, sy + y1);
+                start = if self.map.in_bounds(sx, sy) && self.regions[self.map.xy_idx(sx, sy)] == region {
+                    Some(Position::new(sx, sy))
+                } else {
+                    None
+                };
+            }
+            self.map.take_snapshot();
+        }
+
+        // Wall off region borders and the edge of the map, so the only ways between regions
+        // are the corridors carved below
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let idx = self.map.xy_idx(x, y);
+                let edge = x == 0 || y == 0 || x == self.width - 1 || y == self.height - 1;
+                let border = self.map.adjacent(x, y).iter()
+                    .any(|p| self.regions[self.map.xy_idx(p.x, p.y)] != self.regions[idx]);
+                if edge || border {
+                    self.map.tiles[idx] = TileType::Wall;
+                }
+            }
+        }
+
+        // Start where the first region's architect started, unless that got walled over
+        let start = start
+            .filter(|p| self.map.get_tile(p.x, p.y) == TileType::Floor)
+            .or_else(|| self.map.tiles.iter()
+                .position(|t| *t == TileType::Floor)
+                .map(|idx| Position::new(idx as i32 % self.width, idx as i32 / self.width)))
+            .unwrap_or(Position::new(self.width / 2, self.height / 2));
+        self.map.start_position = start;
+        let start_idx = self.map.xy_idx(start.x, start.y);
+        self.map.tiles[start_idx] = TileType::Floor;
+
+        connect_walkable_areas(&mut self.map, start_idx);
+        self.map.take_snapshot();
+
+        let exit_idx = remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx);
+        self.map.set_tile_at_idx(exit_idx, TileType::Exit);
+        self.map.take_snapshot();
+    }
+
+    fn get_map(&self) -> &Map {
+        &self.map
+    }
+
+    fn get_map_mut(&mut self) -> &mut Map {
+        &mut self.map
+    }
+
+    fn start_position(&self) -> (i32, i32) {
+        (self.map.start_position.x, self.map.start_position.y)
+    }
+}
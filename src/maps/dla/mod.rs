@@ -1,171 +1,296 @@
-/// Diffusion-Limited Aggregation Maps
+//! Diffusion-Limited Aggregation Maps
 
-use super::{utils::*, Architect};
-use bracket_pathfinding::prelude::*;
+use super::{draw::line_points, utils::*, Architect};
 
-#[derive(PartialEq, Copy, Clone)]
-pub(crate) enum DlaAlgorithm {
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum DlaAlgorithm {
     WalkInwards,
     WalkOutwards,
     CentralAttractor
 }
 
+/// Something particles head for
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Attractor {
+    Point(Position),
+    /// Every tile along the line between two points, so growth spreads out along it
+    Line(Position, Position)
+}
+
+impl Attractor {
+    /// The tile of the attractor nearest to `(x, y)`
+    pub fn nearest(self, x: i32, y: i32) -> Position {
+        match self {
+            Attractor::Point(p) => p,
+            Attractor::Line(a, b) => {
+                let (dx, dy) = ((b.x - a.x) as f32, (b.y - a.y) as f32);
+                let length = dx * dx + dy * dy;
+                if length == 0.0 { return a; }
+                let t = (((x - a.x) as f32 * dx + (y - a.y) as f32 * dy) / length).clamp(0.0, 1.0);
+                Position::new(a.x + (dx * t).round() as i32, a.y + (dy * t).round() as i32)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DlaSettings {
+    pub algorithm: DlaAlgorithm,
+    pub brush_size: i32,
+    pub symmetry: Symmetry,
+    /// Fraction of the map to turn into floor before stopping
+    pub floor_percent: f32,
+    /// Where growth starts; the first one is the start position. Left empty, growth starts
+    /// in the centre. Separate clusters are joined up with corridors at the end.
+    pub seeds: Vec<Position>,
+    /// What `CentralAttractor` particles fly towards, and what `bias` pulls walkers towards.
+    /// Left empty, that's the start.
+    pub attractors: Vec<Attractor>,
+    /// Chance a particle sticks when it touches the cave; otherwise it carries on
+    pub stickiness: f32,
+    /// Chance each step of a walker heads for the nearest attractor rather than a random way.
+    /// Negative values push walkers away instead.
+    pub bias: f32,
+    /// Steps a particle takes before giving up without carving anything
+    pub max_walker_steps: usize,
+    /// Particles released before the build stops, even if `floor_percent` wasn't reached
+    pub max_walkers: usize
+}
+
 pub struct DlaMap {
     pub map: Map,
     pub width: i32,
     pub height: i32,
-    algorithm: DlaAlgorithm,
-    symmetry: Symmetry,
-    brush_size: i32,
-    floor_percent: f32
+    pub settings: DlaSettings
 }
 
 impl DlaMap {
-    pub fn walk_inwards(width: i32, height: i32) -> Self {
+    pub fn with_settings(width: i32, height: i32, settings: DlaSettings) -> Self {
         Self {
             map: Map::new(width, height),
             width,
             height,
-            algorithm: DlaAlgorithm::WalkInwards,
-            brush_size: 1,
-            symmetry: Symmetry::None,
-            floor_percent: 0.45
+            settings
         }
     }
 
+    /// A preset with the growth controls left at their plain defaults
+    fn preset(width: i32, height: i32, algorithm: DlaAlgorithm, brush_size: i32, symmetry: Symmetry, floor_percent: f32) -> Self {
+        Self::with_settings(width, height, DlaSettings {
+            algorithm,
+            brush_size,
+            symmetry,
+            floor_percent,
+            seeds: Vec::new(),
+            attractors: Vec::new(),
+            stickiness: 1.0,
+            bias: 0.0,
+            max_walker_steps: 20_000,
+            max_walkers: 200_000
+        })
+    }
+
+    pub fn walk_inwards(width: i32, height: i32) -> Self {
+        Self::preset(width, height, DlaAlgorithm::WalkInwards, 1, Symmetry::None, 0.45)
+    }
+
     pub fn walk_outwards(width: i32, height: i32) -> Self {
-        Self {
-            map: Map::new(width, height),
-            width,
-            height,
-            algorithm: DlaAlgorithm::WalkOutwards,
-            brush_size: 2,
-            symmetry: Symmetry::None,
-            floor_percent: 0.45
-        }
+        Self::preset(width, height, DlaAlgorithm::WalkOutwards, 2, Symmetry::None, 0.45)
     }
 
     pub fn central_attractor(width: i32, height: i32) -> Self {
-        Self {
-            map: Map::new(width, height),
-            width,
-            height,
-            algorithm: DlaAlgorithm::CentralAttractor,
-            brush_size: 2,
-            symmetry: Symmetry::None,
-            floor_percent: 0.45
-        }
+        Self::preset(width, height, DlaAlgorithm::CentralAttractor, 2, Symmetry::None, 0.45)
     }
 
     pub fn insectoid(width: i32, height: i32) -> Self {
-        Self {
-            map: Map::new(width, height),
-            width,
-            height,
-            algorithm: DlaAlgorithm::CentralAttractor,
-            brush_size: 2,
-            symmetry: Symmetry::Horizontal,
-            floor_percent: 0.25
+        Self::preset(width, height, DlaAlgorithm::CentralAttractor, 2, Symmetry::Horizontal, 0.25)
+    }
+
+    /// Branching coral growing from the centre towards each corner
+    pub fn coral(width: i32, height: i32) -> Self {
+        let mut coral = Self::preset(width, height, DlaAlgorithm::CentralAttractor, 1, Symmetry::None, 0.3);
+        let (right, bottom) = (width - 3, height - 3);
+        coral.settings.attractors = [(2, 2), (right, 2), (2, bottom), (right, bottom)].iter()
+            .map(|(x, y)| Attractor::Line(Position::new(width / 2, height / 2), Position::new(*x, *y)))
+            .collect();
+        coral.settings.stickiness = 0.4;
+        coral
+    }
+
+    /// Builds on a hex grid instead of a square one
+    pub fn on_hex_grid(mut self) -> Self {
+        self.map = Map::new_hex(self.width, self.height);
+        self
+    }
+
+    fn in_walk_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 2 && x <= self.width - 2 && y >= 2 && y <= self.height - 2
+    }
+
+    fn stagger(&self, x: i32, y: i32, rng: &mut RandomNumberGenerator) -> (i32, i32) {
+        let direction = rng.roll_dice(1, self.map.direction_count()) - 1;
+        let (next_x, next_y) = self.map.step(x, y, direction);
+        if self.in_walk_bounds(next_x, next_y) {
+            (next_x, next_y)
+        } else {
+            (x, y)
+        }
+    }
+
+    /// A random step, or with `bias` chance a step towards (or away from) `target`
+    fn walk(&self, x: i32, y: i32, target: Position, rng: &mut RandomNumberGenerator) -> (i32, i32) {
+        if !roll(self.settings.bias.abs(), rng) {
+            return self.stagger(x, y, rng);
+        }
+
+        let pull = if self.settings.bias > 0.0 { 1 } else { -1 };
+        (0..self.map.direction_count())
+            .map(|direction| self.map.step(x, y, direction))
+            .filter(|(nx, ny)| self.in_walk_bounds(*nx, *ny))
+            .min_by_key(|(nx, ny)| pull * ((nx - target.x).pow(2) + (ny - target.y).pow(2)))
+            .unwrap_or((x, y))
+    }
+
+    fn sticks(&self, rng: &mut RandomNumberGenerator) -> bool {
+        self.settings.stickiness >= 1.0 || roll(self.settings.stickiness, rng)
+    }
+
+    /// Nearest point of the nearest attractor, or the start if there are none
+    fn target(&self, x: i32, y: i32, start: Position) -> Position {
+        self.settings.attractors.iter()
+            .map(|a| a.nearest(x, y))
+            .min_by_key(|p| (p.x - x).pow(2) + (p.y - y).pow(2))
+            .unwrap_or(start)
+    }
+
+    /// Releases one particle, returning where it stuck, if it did
+    fn release(&self, seeds: &[Position], rng: &mut RandomNumberGenerator) -> Option<(i32, i32)> {
+        let start = seeds[0];
+        let floor = |x: i32, y: i32| self.map.get_tile(x, y) == TileType::Floor;
+
+        match self.settings.algorithm {
+            DlaAlgorithm::WalkInwards => {
+                let (mut x, mut y) = (rng.roll_dice(1, self.width - 3) + 1, rng.roll_dice(1, self.height - 3) + 1);
+                let (mut prev_x, mut prev_y) = (x, y);
+
+                for _ in 0..self.settings.max_walker_steps {
+                    if floor(x, y) {
+                        if self.sticks(rng) { return Some((prev_x, prev_y)); }
+                        (x, y) = (prev_x, prev_y);
+                    }
+                    (prev_x, prev_y) = (x, y);
+                    (x, y) = self.walk(x, y, self.target(x, y, start), rng);
+                }
+                None
+            },
+            DlaAlgorithm::WalkOutwards => {
+                let from = if seeds.len() > 1 { seeds[rng.range(0, seeds.len() as i32) as usize] } else { start };
+                let (mut x, mut y) = (from.x, from.y);
+                let (mut prev_x, mut prev_y) = (x, y);
+
+                for _ in 0..self.settings.max_walker_steps {
+                    if !floor(x, y) {
+                        if self.sticks(rng) { return Some((x, y)); }
+                        (x, y) = (prev_x, prev_y);
+                    }
+                    (prev_x, prev_y) = (x, y);
+                    (x, y) = self.walk(x, y, self.target(x, y, start), rng);
+                }
+                None
+            },
+            DlaAlgorithm::CentralAttractor => {
+                let (mut x, mut y) = (rng.roll_dice(1, self.width - 3) + 1, rng.roll_dice(1, self.height - 3) + 1);
+                let (mut prev_x, mut prev_y) = (x, y);
+                let target = self.target(x, y, start);
+                let mut path = line_points(self.map.grid, x, y, target.x, target.y).into_iter();
+
+                for _ in 0..self.settings.max_walker_steps {
+                    if floor(x, y) {
+                        if self.sticks(rng) { return Some((prev_x, prev_y)); }
+                        // Bounce off and fly in again from wherever it ends up
+                        (x, y) = self.stagger(prev_x, prev_y, rng);
+                        let target = self.target(x, y, start);
+                        path = line_points(self.map.grid, x, y, target.x, target.y).into_iter();
+                    }
+
+                    // Reaching the attractor without touching the cave starts a new branch there
+                    let Some(next) = path.next() else { return Some((prev_x, prev_y)) };
+                    (prev_x, prev_y) = (x, y);
+                    (x, y) = (next.x, next.y);
+                }
+                None
+            }
         }
     }
 }
 
 impl Architect for DlaMap {
-    fn build(&mut self) {
-        let mut rng = RandomNumberGenerator::new();
-    
-        // Carve a starting seed
-        let start_position = Position::new(self.width / 2, self.height / 2);
+    fn build_with_rng(&mut self, rng: &mut RandomNumberGenerator) {
+        self.map.clear();
+
+        // Carve the starting seeds
+        let seeds: Vec<Position> = if self.settings.seeds.is_empty() {
+            vec![Position::new(self.width / 2, self.height / 2)]
+        } else {
+            self.settings.seeds.iter()
+                .map(|p| Position::new(p.x.clamp(2, self.width - 3), p.y.clamp(2, self.height - 3)))
+                .collect()
+        };
+        let start_position = seeds[0];
         let start_idx = self.map.xy_idx(start_position.x, start_position.y);
         self.map.start_position = start_position;
 
-        self.map.set_tile_at_idx(start_idx, TileType::Floor);
-        self.map.set_tile_at_idx(start_idx - 1, TileType::Floor);
-        self.map.set_tile_at_idx(start_idx + 1, TileType::Floor);
-        self.map.set_tile_at_idx(start_idx - self.width as usize, TileType::Floor);
-        self.map.set_tile_at_idx(start_idx + self.width as usize, TileType::Floor);
-    
+        for seed in seeds.iter() {
+            paint(&mut self.map, self.settings.symmetry, 1, seed.x, seed.y);
+            for p in self.map.adjacent(seed.x, seed.y) {
+                paint(&mut self.map, self.settings.symmetry, 1, p.x, p.y);
+            }
+        }
+
         // Random walker
         let total_tiles = self.width * self.height;
-        let desired_floor_tiles = (self.floor_percent * total_tiles as f32) as usize;
+        let desired_floor_tiles = (self.settings.floor_percent * total_tiles as f32) as usize;
         let mut floor_tile_count = self.map.count_tile_type(TileType::Floor);
 
-        while floor_tile_count < desired_floor_tiles {    
-            match self.algorithm {
-                DlaAlgorithm::WalkInwards => {
-                    let mut digger_x = rng.roll_dice(1, self.width - 3) + 1;
-                    let mut digger_y = rng.roll_dice(1, self.height - 3) + 1;
-                    let mut prev_x = digger_x;
-                    let mut prev_y = digger_y;
-
-                    while self.map.get_tile(digger_x, digger_y) == TileType::Wall {
-                        prev_x = digger_x;
-                        prev_y = digger_y;
-                        let stagger_direction = rng.roll_dice(1, 4);
-
-                        match stagger_direction {
-                            1 => { if digger_x > 2 { digger_x -= 1; } }
-                            2 => { if digger_x < self.width - 2 { digger_x += 1; } }
-                            3 => { if digger_y > 2 { digger_y -= 1; } }
-                            _ => { if digger_y < self.height - 2 { digger_y += 1; } }
-                        }
-                    }
+        // Each particle only adds a tile or two, so snapshot every 1% of the map instead
+        let snapshot_every = usize::max(1, total_tiles as usize / 100);
+        let mut next_snapshot = floor_tile_count + snapshot_every;
 
-                    paint(&mut self.map, self.symmetry, self.brush_size, prev_x, prev_y);
-                },
-                DlaAlgorithm::WalkOutwards => {
-                    let mut digger_x = start_position.x;
-                    let mut digger_y = start_position.y;
-
-                    while self.map.get_tile(digger_x, digger_y) == TileType::Floor {
-                        let stagger_direction = rng.roll_dice(1, 4);
-                        match stagger_direction {
-                            1 => { if digger_x > 2 { digger_x -= 1; } }
-                            2 => { if digger_x < self.width - 2 { digger_x += 1; } }
-                            3 => { if digger_y > 2 { digger_y -= 1; } }
-                            _ => { if digger_y < self.height - 2 { digger_y += 1; } }
-                        }
-                    }
-                    paint(&mut self.map, self.symmetry, self.brush_size, digger_x, digger_y);
-                },
-                DlaAlgorithm::CentralAttractor => {
-                    let mut digger_x = rng.roll_dice(1, self.width - 3) + 1;
-                    let mut digger_y = rng.roll_dice(1, self.height - 3) + 1;
-                    let mut prev_x = digger_x;
-                    let mut prev_y = digger_y;
-                
-                    let mut path = line2d(
-                        LineAlg::Bresenham, 
-                        Point::new( digger_x, digger_y ), 
-                        Point::new( start_position.x, start_position.y )
-                    );
-                
-                    while self.map.get_tile(digger_x, digger_y) == TileType::Wall && !path.is_empty() {
-                        prev_x = digger_x;
-                        prev_y = digger_y;
-                        digger_x = path[0].x;
-                        digger_y = path[0].y;
-                        path.remove(0);
-                    }
-
-                    paint(&mut self.map, self.symmetry, self.brush_size, prev_x, prev_y);
-                }
+        let mut walkers = 0;
+        while floor_tile_count < desired_floor_tiles && walkers < self.settings.max_walkers {
+            walkers += 1;
+            if let Some((x, y)) = self.release(&seeds, rng) {
+                floor_tile_count += paint(&mut self.map, self.settings.symmetry, self.settings.brush_size, x, y).len();
             }
 
-            floor_tile_count = self.map.count_tile_type(TileType::Floor);
+            if floor_tile_count >= next_snapshot {
+                self.map.take_snapshot();
+                next_snapshot = floor_tile_count + snapshot_every;
+            }
         }
 
-        // Find all tiles we can reach from the starting point
+        // Join up separate clusters and mirrored copies of the start, mirroring the corridors
+        // too, then find all tiles we can reach from the starting point
+        connect_images(&mut self.map, self.settings.symmetry, start_position);
+        for idx in connect_walkable_areas(&mut self.map, start_idx) {
+            let (x, y) = (idx as i32 % self.width, idx as i32 / self.width);
+            paint(&mut self.map, self.settings.symmetry, 1, x, y);
+        }
         let exit_tile = remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx);
 
         // Place the stairs
         self.map.set_tile_at_idx(exit_tile, TileType::Exit);
+        self.map.take_snapshot();
     }
 
     fn get_map(&self) -> &Map {
         &self.map
     }
 
+    fn get_map_mut(&mut self) -> &mut Map {
+        &mut self.map
+    }
+
     fn start_position(&self) -> (i32, i32) {
         (self.map.start_position.x, self.map.start_position.y)
     }
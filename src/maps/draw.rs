@@ -0,0 +1,190 @@
+//! Drawing primitives
+//!
+//! Brushes, lines, rectangles, circles, flood fill and corridors for generators and editors
+//! to share. Everything clips to the map, so shapes can hang off the edge safely, and returns
+//! the indices of the tiles it actually changed. Nothing here keeps the border walled, so
+//! generators still have to stay off the edge themselves.
+
+use std::cmp::{max, min};
+use std::collections::VecDeque;
+
+use bracket_pathfinding::prelude::{line2d, LineAlg, Point};
+
+use super::hex::{hex_distance, hex_line};
+use super::utils::{GridType, Map, Position, TileType};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BrushShape {
+    Square,
+    Circle,
+    Diamond
+}
+
+/// A shape centred on the tile being painted. A radius of 0 is a single tile and each step
+/// adds a ring, so brushes are always an odd number of tiles across. On hex grids every
+/// shape is a filled hexagon, as hex distance already makes a hexagon the natural circle.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Brush {
+    pub shape: BrushShape,
+    pub radius: i32
+}
+
+impl Brush {
+    pub fn square(radius: i32) -> Self {
+        Self { shape: BrushShape::Square, radius }
+    }
+
+    pub fn circle(radius: i32) -> Self {
+        Self { shape: BrushShape::Circle, radius }
+    }
+
+    pub fn diamond(radius: i32) -> Self {
+        Self { shape: BrushShape::Diamond, radius }
+    }
+
+    /// The square brush generators mean by a `brush_size`, i.e. radius half the size
+    pub fn from_size(brush_size: i32) -> Self {
+        Self::square(brush_size / 2)
+    }
+
+    /// Tiles under the brush at `(x, y)`, without clipping to any map
+    pub fn footprint(self, grid: GridType, x: i32, y: i32) -> Vec<Position> {
+        let r = max(self.radius, 0);
+        let centre = Position::new(x, y);
+        let mut tiles = Vec::new();
+
+        if grid == GridType::Hex {
+            // Odd-r rows are offset by half a tile, so look one column further either side
+            for ty in y - r ..= y + r {
+                for tx in x - r - 1 ..= x + r + 1 {
+                    let tile = Position::new(tx, ty);
+                    if hex_distance(centre, tile) <= r { tiles.push(tile); }
+                }
+            }
+            return tiles;
+        }
+
+        for dy in -r ..= r {
+            for dx in -r ..= r {
+                let inside = match self.shape {
+                    BrushShape::Square => true,
+                    // Half a tile of slack keeps small circles from looking like diamonds
+                    BrushShape::Circle => dx * dx + dy * dy <= r * r + r,
+                    BrushShape::Diamond => dx.abs() + dy.abs() <= r
+                };
+                if inside { tiles.push(Position::new(x + dx, y + dy)); }
+            }
+        }
+        tiles
+    }
+}
+
+/// Sets a single tile, returning its index if it changed
+pub fn plot(map: &mut Map, x: i32, y: i32, tile: TileType) -> Option<usize> {
+    if !map.in_bounds(x, y) { return None; }
+    let idx = map.xy_idx(x, y);
+    if map.tiles[idx] == tile { return None; }
+    map.tiles[idx] = tile;
+    Some(idx)
+}
+
+fn plot_all<I>(map: &mut Map, tiles: I, tile: TileType) -> Vec<usize>
+where
+    I: IntoIterator<Item = Position>
+{
+    tiles.into_iter().filter_map(|p| plot(map, p.x, p.y, tile)).collect()
+}
+
+pub fn stamp(map: &mut Map, brush: Brush, x: i32, y: i32, tile: TileType) -> Vec<usize> {
+    let footprint = brush.footprint(map.grid, x, y);
+    plot_all(map, footprint, tile)
+}
+
+/// Tiles on the straight line between two points, inclusive. Square grids use Bresenham's
+/// algorithm; hex grids step through neighbouring hexes.
+pub fn line_points(grid: GridType, x1: i32, y1: i32, x2: i32, y2: i32) -> Vec<Position> {
+    match grid {
+        GridType::Square => line2d(LineAlg::Bresenham, Point::new(x1, y1), Point::new(x2, y2))
+            .into_iter()
+            .map(|p| Position::new(p.x, p.y))
+            .collect(),
+        GridType::Hex => hex_line(Position::new(x1, y1), Position::new(x2, y2))
+    }
+}
+
+pub fn line(map: &mut Map, x1: i32, y1: i32, x2: i32, y2: i32, tile: TileType) -> Vec<usize> {
+    let points = line_points(map.grid, x1, y1, x2, y2);
+    plot_all(map, points, tile)
+}
+
+/// Fills the rectangle with corners `(x1, y1)` and `(x2, y2)`, inclusive
+pub fn fill_rect(map: &mut Map, x1: i32, y1: i32, x2: i32, y2: i32, tile: TileType) -> Vec<usize> {
+    let points: Vec<Position> = (min(y1, y2) ..= max(y1, y2))
+        .flat_map(|y| (min(x1, x2) ..= max(x1, x2)).map(move |x| Position::new(x, y)))
+        .collect();
+    plot_all(map, points, tile)
+}
+
+/// Draws just the edge of the rectangle with corners `(x1, y1)` and `(x2, y2)`, inclusive
+pub fn outline_rect(map: &mut Map, x1: i32, y1: i32, x2: i32, y2: i32, tile: TileType) -> Vec<usize> {
+    let (left, right) = (min(x1, x2), max(x1, x2));
+    let (top, bottom) = (min(y1, y2), max(y1, y2));
+    let points: Vec<Position> = (top ..= bottom)
+        .flat_map(|y| (left ..= right).map(move |x| Position::new(x, y)))
+        .filter(|p| p.x == left || p.x == right || p.y == top || p.y == bottom)
+        .collect();
+    plot_all(map, points, tile)
+}
+
+pub fn fill_circle(map: &mut Map, x: i32, y: i32, radius: i32, tile: TileType) -> Vec<usize> {
+    stamp(map, Brush::circle(radius), x, y, tile)
+}
+
+/// Replaces the area of matching tiles around `(x, y)` with `tile`, spreading to neighbours
+/// the way the map's grid moves
+pub fn flood_fill(map: &mut Map, x: i32, y: i32, tile: TileType) -> Vec<usize> {
+    if !map.in_bounds(x, y) { return Vec::new(); }
+    let target = map.get_tile(x, y);
+    if target == tile { return Vec::new(); }
+
+    let mut changed = Vec::new();
+    let mut open = VecDeque::from([Position::new(x, y)]);
+    map.set_tile(x, y, tile);
+    changed.push(map.xy_idx(x, y));
+
+    while let Some(p) = open.pop_front() {
+        for next in map.adjacent(p.x, p.y) {
+            if map.get_tile(next.x, next.y) == target {
+                map.set_tile(next.x, next.y, tile);
+                changed.push(map.xy_idx(next.x, next.y));
+                open.push_back(next);
+            }
+        }
+    }
+    changed
+}
+
+/// An L shaped corridor from `from` to `to`, running along one axis and then the other,
+/// painted with `brush` at every step. Both ends are included.
+pub fn corridor(map: &mut Map, from: Position, to: Position, horizontal_first: bool, brush: Brush, tile: TileType) -> Vec<usize> {
+    let corner = if horizontal_first { Position::new(to.x, from.y) } else { Position::new(from.x, to.y) };
+    let mut points = straight(from, corner);
+    points.extend(straight(corner, to).into_iter().skip(1));
+
+    let mut changed = Vec::new();
+    for p in points {
+        changed.extend(stamp(map, brush, p.x, p.y, tile));
+    }
+    changed
+}
+
+/// Tiles from `a` to `b`, inclusive, where the two share a row or a column
+fn straight(a: Position, b: Position) -> Vec<Position> {
+    if a.y == b.y {
+        let step = if b.x >= a.x { 1 } else { -1 };
+        (0 ..= (b.x - a.x).abs()).map(|i| Position::new(a.x + i * step, a.y)).collect()
+    } else {
+        let step = if b.y >= a.y { 1 } else { -1 };
+        (0 ..= (b.y - a.y).abs()).map(|i| Position::new(a.x, a.y + i * step)).collect()
+    }
+}
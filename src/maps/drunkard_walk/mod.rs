@@ -1,170 +1,337 @@
-use crate::maps::{utils::*, Architect};
+use crate::maps::{draw::Brush, utils::*, Architect};
 
-#[derive(PartialEq, Copy, Clone)]
-pub(crate) enum DrunkSpawnMode { StartingPoint, Random }
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum DrunkSpawnMode { StartingPoint, Random }
 
-pub(crate) struct DrunkardSettings {
-    spawn_mode : DrunkSpawnMode,
-    lifetime: i32,
-    floor_percent: f32,
-    brush_size: i32,
-    symmetry: Symmetry
+/// One kind of digger. Several kinds can take turns in a build, e.g. narrow tunnellers
+/// alongside diggers which open out chambers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrunkardSpecies {
+    pub brush: Brush,
+    /// Steps a digger takes before the next one is spawned
+    pub lifetime: i32,
+    /// Relative chance of stepping each way, in `Map::step` order followed by the diagonals
+    /// up-left, up-right, down-left and down-right. Missing entries count as 1; left empty,
+    /// every way is as likely.
+    pub direction_weights: Vec<f32>,
+    /// Chance of carrying on the way the digger last went, for long straight tunnels
+    pub momentum: f32,
+    /// Lets diggers on a square grid step diagonally. The corner is carved too, so the
+    /// tunnel stays connected.
+    pub diagonals: bool,
+    /// Chance each step of a child splitting off, which digs for half of what's left of the
+    /// parent's life
+    pub branch_chance: f32,
+    /// Chance of turning aside when about to step onto floor which is already dug
+    pub avoid_carved: f32
+}
+
+impl DrunkardSpecies {
+    /// A plain random walker
+    pub fn new(brush: Brush, lifetime: i32) -> Self {
+        Self {
+            brush,
+            lifetime,
+            direction_weights: Vec::new(),
+            momentum: 0.0,
+            diagonals: false,
+            branch_chance: 0.0,
+            avoid_carved: 0.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrunkardSettings {
+    /// Where each new digger starts; `Random` still sends the first one from the start
+    pub spawn_mode : DrunkSpawnMode,
+    /// Steps a digger takes before the next one is spawned
+    pub lifetime: i32,
+    /// Fraction of the map to turn into floor before stopping
+    pub floor_percent: f32,
+    /// Diggers sent out before the build stops, even if `floor_percent` wasn't reached
+    pub max_diggers: usize,
+    pub brush_size: i32,
+    pub symmetry: Symmetry,
+    /// Kinds of digger, taking turns in order. Left empty, every digger is a plain random
+    /// walker with `lifetime` and `brush_size`.
+    pub species: Vec<DrunkardSpecies>
+}
+
+/// A digger part way through its life
+#[derive(Copy, Clone)]
+struct Digger {
+    x: i32,
+    y: i32,
+    life: i32,
+    /// The way it last moved
+    heading: Option<i32>
 }
 
 pub struct DrunkardWalkMap {
     pub map: Map,
     pub width: i32,
     pub height: i32,
-    settings: DrunkardSettings
+    pub settings: DrunkardSettings
 }
 
 impl DrunkardWalkMap {
-    pub fn open_area(width: i32, height: i32) -> Self {
+    pub fn with_settings(width: i32, height: i32, settings: DrunkardSettings) -> Self {
         Self {
             map: Map::new(width, height),
             width,
             height,
-            settings: DrunkardSettings {
-                spawn_mode: DrunkSpawnMode::StartingPoint,
-                lifetime: 400,
-                floor_percent: 0.5,
-                brush_size: 1,
-                symmetry: Symmetry::None
-            }
+            settings
         }
     }
 
+    pub fn open_area(width: i32, height: i32) -> Self {
+        Self::with_settings(width, height, DrunkardSettings {
+            spawn_mode: DrunkSpawnMode::StartingPoint,
+            lifetime: 400,
+            floor_percent: 0.5,
+            max_diggers: 10_000,
+            brush_size: 1,
+            symmetry: Symmetry::None,
+            species: Vec::new()
+        })
+    }
+
     pub fn open_halls(width: i32, height: i32) -> Self {
-        Self {
-            map: Map::new(width, height),
-            width,
-            height,
-            settings: DrunkardSettings {
-                spawn_mode: DrunkSpawnMode::Random,
-                lifetime: 400,
-                floor_percent: 0.5,
-                brush_size: 1,
-                symmetry: Symmetry::None
-            }
-        }
+        Self::with_settings(width, height, DrunkardSettings {
+            spawn_mode: DrunkSpawnMode::Random,
+            lifetime: 400,
+            floor_percent: 0.5,
+            max_diggers: 10_000,
+            brush_size: 1,
+            symmetry: Symmetry::None,
+            species: Vec::new()
+        })
     }
 
     pub fn winding_passages(width: i32, height: i32) -> Self {
-        Self {
-            map: Map::new(width, height),
-            width,
-            height,
-            settings: DrunkardSettings {
-                spawn_mode: DrunkSpawnMode::Random,
-                lifetime: 100,
-                floor_percent: 0.4,
-                brush_size: 1,
-                symmetry: Symmetry::None
-            }
-        }
+        Self::with_settings(width, height, DrunkardSettings {
+            spawn_mode: DrunkSpawnMode::Random,
+            lifetime: 100,
+            floor_percent: 0.4,
+            max_diggers: 10_000,
+            brush_size: 1,
+            symmetry: Symmetry::None,
+            species: Vec::new()
+        })
     }
 
     pub fn fat_passages(width: i32, height: i32) -> Self {
-        Self {
-            map: Map::new(width, height),
-            width,
-            height,
-            settings: DrunkardSettings {
-                spawn_mode: DrunkSpawnMode::Random,
-                lifetime: 400,
-                floor_percent: 0.4,
-                brush_size: 2,
-                symmetry: Symmetry::None
-            }
-        }
+        Self::with_settings(width, height, DrunkardSettings {
+            spawn_mode: DrunkSpawnMode::Random,
+            lifetime: 400,
+            floor_percent: 0.4,
+            max_diggers: 10_000,
+            brush_size: 2,
+            symmetry: Symmetry::None,
+            species: Vec::new()
+        })
     }
 
     pub fn fearful_symmetry(width: i32, height: i32) -> Self {
-        Self {
-            map: Map::new(width, height),
-            width,
-            height,
-            settings: DrunkardSettings {
-                spawn_mode: DrunkSpawnMode::Random,
-                lifetime: 400,
-                floor_percent: 0.4,
-                brush_size: 1,
-                symmetry: Symmetry::Both
-            }
+        Self::with_settings(width, height, DrunkardSettings {
+            spawn_mode: DrunkSpawnMode::Random,
+            lifetime: 400,
+            floor_percent: 0.4,
+            max_diggers: 10_000,
+            brush_size: 1,
+            symmetry: Symmetry::Both,
+            species: Vec::new()
+        })
+    }
+
+    /// Straight mine shafts branching off one another
+    pub fn mines(width: i32, height: i32) -> Self {
+        Self::with_settings(width, height, DrunkardSettings {
+            spawn_mode: DrunkSpawnMode::StartingPoint,
+            lifetime: 150,
+            floor_percent: 0.3,
+            max_diggers: 10_000,
+            brush_size: 1,
+            symmetry: Symmetry::None,
+            species: vec![DrunkardSpecies {
+                momentum: 0.85,
+                branch_chance: 0.03,
+                avoid_carved: 0.9,
+                ..DrunkardSpecies::new(Brush::square(0), 150)
+            }]
+        })
+    }
+
+    /// Long tunnels between round chambers
+    pub fn tunnels_and_chambers(width: i32, height: i32) -> Self {
+        Self::with_settings(width, height, DrunkardSettings {
+            spawn_mode: DrunkSpawnMode::Random,
+            lifetime: 200,
+            floor_percent: 0.4,
+            max_diggers: 10_000,
+            brush_size: 1,
+            symmetry: Symmetry::None,
+            species: vec![
+                DrunkardSpecies {
+                    momentum: 0.9,
+                    avoid_carved: 0.5,
+                    ..DrunkardSpecies::new(Brush::square(0), 200)
+                },
+                DrunkardSpecies::new(Brush::circle(2), 30)
+            ]
+        })
+    }
+
+    /// Builds on a hex grid instead of a square one
+    pub fn on_hex_grid(mut self) -> Self {
+        self.map = Map::new_hex(self.width, self.height);
+        self
+    }
+
+    fn species(&self) -> Vec<DrunkardSpecies> {
+        if self.settings.species.is_empty() {
+            vec![DrunkardSpecies::new(Brush::from_size(self.settings.brush_size), self.settings.lifetime)]
+        } else {
+            self.settings.species.clone()
         }
     }
+
+    fn direction_count(&self, species: &DrunkardSpecies) -> i32 {
+        if species.diagonals && self.map.grid == GridType::Square { 8 } else { self.map.direction_count() }
+    }
+
+    fn step(&self, x: i32, y: i32, direction: i32) -> (i32, i32) {
+        match direction {
+            4 if self.map.grid == GridType::Square => (x - 1, y - 1),
+            5 if self.map.grid == GridType::Square => (x + 1, y - 1),
+            6 if self.map.grid == GridType::Square => (x - 1, y + 1),
+            7 if self.map.grid == GridType::Square => (x + 1, y + 1),
+            _ => self.map.step(x, y, direction)
+        }
+    }
+
+    fn in_dig_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 2 && x <= self.width - 2 && y >= 2 && y <= self.height - 2
+    }
+
+    /// Picks one of `directions`, weighted by the species' direction weights
+    fn pick(species: &DrunkardSpecies, directions: &[i32], rng: &mut RandomNumberGenerator) -> i32 {
+        let weight = |d: &i32| species.direction_weights.get(*d as usize).copied().unwrap_or(1.0).max(0.0);
+        let total: f32 = directions.iter().map(weight).sum();
+        if species.direction_weights.is_empty() || total <= 0.0 {
+            return directions[(rng.roll_dice(1, directions.len() as i32) - 1) as usize];
+        }
+
+        let mut roll = rng.range(0, 10_000) as f32 / 10_000.0 * total;
+        for d in directions {
+            roll -= weight(d);
+            if roll < 0.0 { return *d; }
+        }
+        directions[directions.len() - 1]
+    }
+
+    fn choose_direction(&self, species: &DrunkardSpecies, digger: &Digger, rng: &mut RandomNumberGenerator) -> i32 {
+        if let Some(heading) = digger.heading {
+            if roll(species.momentum, rng) { return heading; }
+        }
+
+        let directions: Vec<i32> = (0..self.direction_count(species)).collect();
+        let direction = Self::pick(species, &directions, rng);
+
+        let (next_x, next_y) = self.step(digger.x, digger.y, direction);
+        let onto_floor = self.in_dig_bounds(next_x, next_y) && self.map.get_tile(next_x, next_y) == TileType::Floor;
+        if onto_floor && roll(species.avoid_carved, rng) {
+            let fresh: Vec<i32> = directions.into_iter()
+                .filter(|d| {
+                    let (x, y) = self.step(digger.x, digger.y, *d);
+                    self.in_dig_bounds(x, y) && self.map.get_tile(x, y) != TileType::Floor
+                })
+                .collect();
+            if !fresh.is_empty() { return Self::pick(species, &fresh, rng); }
+        }
+        direction
+    }
 }
 
 impl Architect for DrunkardWalkMap {
-    fn build(&mut self) {
-        let mut rng = RandomNumberGenerator::new();
+    fn build_with_rng(&mut self, rng: &mut RandomNumberGenerator) {
+        self.map.clear();
 
         // Set a central starting point
         let start_position = Position::new(self.width / 2, self.height / 2);
         let start_idx = self.map.xy_idx(start_position.x, start_position.y);
-        self.map.start_position = start_position.clone();
-        self.map.set_tile_at_idx(start_idx, TileType::Floor);
+        self.map.start_position = start_position;
+        paint(&mut self.map, self.settings.symmetry, 1, start_position.x, start_position.y);
 
         let total_tiles = self.width * self.height;
         let desired_floor_tiles = (self.settings.floor_percent * total_tiles as f32) as usize;
         let mut floor_tile_count = self.map.count_tile_type(TileType::Floor);
         
+        let species = self.species();
         let mut digger_count = 0;
-        while floor_tile_count < desired_floor_tiles {
-            let mut drunk_x;
-            let mut drunk_y;
-
-            match self.settings.spawn_mode {
-                DrunkSpawnMode::StartingPoint => {
-                    drunk_x = start_position.x;
-                    drunk_y = start_position.y;
-                },
+        while floor_tile_count < desired_floor_tiles && digger_count < self.settings.max_diggers {
+            let kind = &species[digger_count % species.len()];
+            let (drunk_x, drunk_y) = match self.settings.spawn_mode {
+                DrunkSpawnMode::StartingPoint => (start_position.x, start_position.y),
                 DrunkSpawnMode::Random => {
                     if digger_count == 0 {
-                        drunk_x = start_position.x;
-                        drunk_y = start_position.y;
+                        (start_position.x, start_position.y)
                     } else {
-                        drunk_x = rng.roll_dice(1, self.width - 3) + 1;
-                        drunk_y = rng.roll_dice(1, self.height - 3) + 1;
+                        (rng.roll_dice(1, self.width - 3) + 1, rng.roll_dice(1, self.height - 3) + 1)
                     }
                 }
-            }
+            };
 
-            let mut drunk_life = self.settings.lifetime;
+            // Children wait their turn until the digger which spawned them has finished
+            let mut diggers = vec![Digger { x: drunk_x, y: drunk_y, life: kind.lifetime, heading: None }];
+            while let Some(mut digger) = diggers.pop() {
+                while digger.life > 0 {
+                    let carved = paint_with(&mut self.map, self.settings.symmetry, kind.brush, digger.x, digger.y);
+                    floor_tile_count += carved.len();
 
-            while drunk_life > 0 {
-                paint(&mut self.map, self.settings.symmetry, self.settings.brush_size, drunk_x, drunk_y);
-                self.map.set_tile(drunk_x, drunk_y, TileType::Exit);
+                    if digger.life > 1 && roll(kind.branch_chance, rng) {
+                        diggers.push(Digger { life: digger.life / 2, heading: None, ..digger });
+                    }
 
-                let stagger_direction = rng.roll_dice(1, 4);
-                match stagger_direction {
-                    1 => { if drunk_x > 2 { drunk_x -= 1; } }
-                    2 => { if drunk_x < self.width-2 { drunk_x += 1; } }
-                    3 => { if drunk_y > 2 { drunk_y -=1; } }
-                    _ => { if drunk_y < self.height-2 { drunk_y += 1; } }
-                }
+                    let direction = self.choose_direction(kind, &digger, rng);
+                    let (next_x, next_y) = self.step(digger.x, digger.y, direction);
+                    if self.in_dig_bounds(next_x, next_y) {
+                        if self.map.grid == GridType::Square && next_x != digger.x && next_y != digger.y {
+                            let carved = paint_with(&mut self.map, self.settings.symmetry, kind.brush, next_x, digger.y);
+                            floor_tile_count += carved.len();
+                        }
+                        digger.x = next_x;
+                        digger.y = next_y;
+                        digger.heading = Some(direction);
+                    } else {
+                        // Turn round rather than keep running into the edge
+                        digger.heading = None;
+                    }
 
-                drunk_life -= 1;
+                    digger.life -= 1;
+                }
             }
 
             digger_count += 1;
-            for t in self.map.tiles.iter_mut() {
-                if *t == TileType::Exit {
-                    *t = TileType::Floor;
-                }
-            }
-            floor_tile_count = self.map.count_tile_type(TileType::Floor);
+            self.map.take_snapshot();
         }
 
-        // Set the exit
+        // Set the exit, keeping every mirrored copy of the start reachable
+        connect_images(&mut self.map, self.settings.symmetry, start_position);
         let exit_idx = remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx);
         self.map.set_tile_at_idx(exit_idx, TileType::Exit);
+        self.map.take_snapshot();
     }
 
     fn get_map(&self) -> &Map {
         &self.map
     }
 
+    fn get_map_mut(&mut self) -> &mut Map {
+        &mut self.map
+    }
+
     fn start_position(&self) -> (i32, i32) {
         (self.map.start_position.x, self.map.start_position.y)
     }
@@ -0,0 +1,136 @@
+//! Hexagonal grid helpers
+//!
+//! Hex maps store their tiles in the same row-major layout as square maps, using
+//! "odd-r" offset coordinates: every odd row is shoved half a tile to the right.
+//! Geometry (neighbours, distance, lines) is done in axial coordinates and
+//! converted back to offsets, so `xy_idx` and friends work unchanged.
+
+use super::utils::{Map, Position, TileType};
+
+/// Axial hex coordinate
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Hex {
+    pub q: i32,
+    pub r: i32
+}
+
+/// Axial offsets of the six neighbours, clockwise starting from the west.
+pub const HEX_DIRECTIONS: [Hex; 6] = [
+    Hex { q: -1, r: 0 },
+    Hex { q: 0, r: -1 },
+    Hex { q: 1, r: -1 },
+    Hex { q: 1, r: 0 },
+    Hex { q: 0, r: 1 },
+    Hex { q: -1, r: 1 }
+];
+
+impl Hex {
+    pub fn new(q: i32, r: i32) -> Self {
+        Self { q, r }
+    }
+
+    /// Converts an odd-r offset coordinate into axial
+    pub fn from_offset(x: i32, y: i32) -> Self {
+        Self { q: x - (y - (y & 1)) / 2, r: y }
+    }
+
+    /// Converts back into an odd-r offset coordinate
+    pub fn to_offset(self) -> (i32, i32) {
+        (self.q + (self.r - (self.r & 1)) / 2, self.r)
+    }
+
+    pub fn neighbour(self, direction: usize) -> Hex {
+        let d = HEX_DIRECTIONS[direction % 6];
+        Hex::new(self.q + d.q, self.r + d.r)
+    }
+
+    /// Number of steps between two hexes
+    pub fn distance(self, other: Hex) -> i32 {
+        let dq = self.q - other.q;
+        let dr = self.r - other.r;
+        (dq.abs() + dr.abs() + (dq + dr).abs()) / 2
+    }
+
+    /// All hexes on a straight line from `self` to `other`, inclusive of both ends.
+    pub fn line_to(self, other: Hex) -> Vec<Hex> {
+        let n = self.distance(other);
+        if n == 0 { return vec![self]; }
+
+        // Nudge the end points slightly so lines running along hex edges break ties consistently
+        let (aq, ar) = (self.q as f32 + 1e-6, self.r as f32 + 1e-6);
+        let (bq, br) = (other.q as f32 + 1e-6, other.r as f32 + 1e-6);
+
+        (0..=n).map(|i| {
+            let t = i as f32 / n as f32;
+            hex_round(aq + (bq - aq) * t, ar + (br - ar) * t)
+        }).collect()
+    }
+}
+
+fn hex_round(q: f32, r: f32) -> Hex {
+    let s = -q - r;
+    let mut rq = q.round();
+    let mut rr = r.round();
+    let rs = s.round();
+
+    let dq = (rq - q).abs();
+    let dr = (rr - r).abs();
+    let ds = (rs - s).abs();
+
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+
+    Hex::new(rq as i32, rr as i32)
+}
+
+/// Distance in steps between two offset positions on a hex grid
+pub fn hex_distance(a: Position, b: Position) -> i32 {
+    Hex::from_offset(a.x, a.y).distance(Hex::from_offset(b.x, b.y))
+}
+
+/// Offset position reached by taking one step in `direction` (0..6)
+pub fn hex_step(x: i32, y: i32, direction: usize) -> (i32, i32) {
+    Hex::from_offset(x, y).neighbour(direction).to_offset()
+}
+
+/// Offset positions on the line between two offset positions, inclusive.
+pub fn hex_line(a: Position, b: Position) -> Vec<Position> {
+    Hex::from_offset(a.x, a.y)
+        .line_to(Hex::from_offset(b.x, b.y))
+        .into_iter()
+        .map(|h| {
+            let (x, y) = h.to_offset();
+            Position::new(x, y)
+        })
+        .collect()
+}
+
+/// Every tile within `radius` steps of the origin that has an unobstructed line back to it.
+/// Opaque tiles are visible themselves, but block anything behind them.
+pub fn hex_field_of_view(map: &Map, origin: Position, radius: i32) -> Vec<Position> {
+    let mut visible = Vec::new();
+    if !map.in_bounds(origin.x, origin.y) { return visible; }
+
+    let centre = Hex::from_offset(origin.x, origin.y);
+    for y in i32::max(0, origin.y - radius) ..= i32::min(map.height - 1, origin.y + radius) {
+        for x in i32::max(0, origin.x - radius - 1) ..= i32::min(map.width - 1, origin.x + radius + 1) {
+            let target = Hex::from_offset(x, y);
+            if centre.distance(target) > radius { continue; }
+
+            let line = centre.line_to(target);
+            let blocked = line.iter().skip(1).take(line.len().saturating_sub(2)).any(|h| {
+                let (lx, ly) = h.to_offset();
+                !map.in_bounds(lx, ly) || map.get_tile(lx, ly) == TileType::Wall
+            });
+
+            if !blocked {
+                visible.push(Position::new(x, y));
+            }
+        }
+    }
+
+    visible
+}
@@ -1,3 +1,5 @@
+use std::collections::VecDeque;
+
 use crate::maps::{utils::*, Architect};
 
 const TOP : usize = 0;
@@ -50,31 +52,49 @@ struct Grid<'a> {
     width: i32,
     height: i32,
     cells: Vec<Cell>,
-    backtrace: Vec<usize>,
+    backtrace: VecDeque<usize>,
     current: usize,
+    links: usize,
+    map: &'a mut Map,
     rng : &'a mut RandomNumberGenerator
 }
 
 impl<'a> Grid<'a> {
-    fn new(width: i32, height:i32, rng: &'a mut RandomNumberGenerator) -> Self {
+    /// Every cell ends up open whichever algorithm is used, so they are carved into the map
+    /// straight away. Passages between cells are carved as the cells are linked.
+    fn new(width: i32, height:i32, map: &'a mut Map, rng: &'a mut RandomNumberGenerator) -> Self {
+        map.tiles.fill(TileType::Wall);
+
         let mut grid = Self {
             width,
             height,
-            cells: Vec::new(),
-            backtrace: Vec::new(),
+            cells: Vec::with_capacity((width * height) as usize),
+            backtrace: VecDeque::new(),
             current: 0,
+            links: 0,
+            map,
             rng
         };
 
         for row in 0..height {
             for column in 0..width {
                 grid.cells.push(Cell::new(row, column));
+                let idx = grid.map_idx(grid.cells.len() - 1);
+                grid.map.set_tile_at_idx(idx, TileType::Floor);
             }
         }
+        grid.map.take_snapshot();
 
         grid
     }
 
+    /// Index of the map tile at the centre of a cell. Cells sit on every other tile, leaving
+    /// room for the walls in between.
+    fn map_idx(&self, idx: usize) -> usize {
+        let cell = &self.cells[idx];
+        self.map.xy_idx((cell.column + 1) * 2, (cell.row + 1) * 2)
+    }
+
     fn calculate_index(&self, row: i32, column: i32) -> i32 {
         if row < 0 || column < 0 || column > self.width - 1 || row > self.height - 1 {
             -1
@@ -105,6 +125,64 @@ impl<'a> Grid<'a> {
         neighbors
     }
 
+    fn neighbours(&self, idx: usize) -> Vec<usize> {
+        let row = self.cells[idx].row;
+        let column = self.cells[idx].column;
+
+        [
+            self.calculate_index(row - 1, column),
+            self.calculate_index(row, column + 1),
+            self.calculate_index(row + 1, column),
+            self.calculate_index(row, column - 1)
+        ].iter().filter(|i| **i != -1).map(|i| *i as usize).collect()
+    }
+
+    fn link(&mut self, a: usize, b: usize) {
+        let (lower_part, higher_part) = self.cells.split_at_mut(std::cmp::max(a, b));
+        let cell1 = &mut lower_part[std::cmp::min(a, b)];
+        let cell2 = &mut higher_part[0];
+        cell1.remove_walls(cell2);
+
+        // The wall tile sits exactly halfway between the two cell centres
+        let gap = (self.map_idx(a) + self.map_idx(b)) / 2;
+        self.map.set_tile_at_idx(gap, TileType::Floor);
+
+        // A snapshot per link would be thousands of frames, so take one per row's worth
+        self.links += 1;
+        if self.links.is_multiple_of(self.width as usize) {
+            self.map.take_snapshot();
+        }
+    }
+
+    fn wall_between(&self, a: usize, b: usize) -> bool {
+        let x = self.cells[a].column - self.cells[b].column;
+        let y = self.cells[a].row - self.cells[b].row;
+
+        if x == 1 { self.cells[a].walls[LEFT] }
+        else if x == -1 { self.cells[a].walls[RIGHT] }
+        else if y == 1 { self.cells[a].walls[TOP] }
+        else { self.cells[a].walls[BOTTOM] }
+    }
+
+    fn is_dead_end(&self, idx: usize) -> bool {
+        self.cells[idx].walls.iter().filter(|w| **w).count() == 3
+    }
+
+    fn random_index(&mut self, len: usize) -> usize {
+        (self.rng.roll_dice(1, len as i32) - 1) as usize
+    }
+
+    fn coin_flip(&mut self) -> bool {
+        self.rng.roll_dice(1, 2) == 1
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.random_index(i + 1);
+            items.swap(i, j);
+        }
+    }
+
     fn find_next_cell(&mut self) -> Option<usize> {
         let neighbors = self.get_available_neighbors();
         if !neighbors.is_empty() {
@@ -117,76 +195,384 @@ impl<'a> Grid<'a> {
         None
     }
 
-    fn generate_maze(&mut self, generator : &mut MazeMap) {
+    /// Recursive backtracker: walk to random unvisited neighbours, and whenever we get stuck
+    /// carry on from the oldest cell left on the trail. Taking from the front of the trail
+    /// rather than the back is what the original generator did, and keeps its mazes.
+    fn generate_maze(&mut self) {
         loop {
             self.cells[self.current].visited = true;
             let next = self.find_next_cell();
-    
+
             match next {
                 Some(next) => {
                     self.cells[next].visited = true;
-                    self.backtrace.push(self.current);
-                    let (lower_part, higher_part) =
-                        self.cells.split_at_mut(std::cmp::max(self.current, next));
-                    let cell1 = &mut lower_part[std::cmp::min(self.current, next)];
-                    let cell2 = &mut higher_part[0];
-                    cell1.remove_walls(cell2);
+                    self.backtrace.push_back(self.current);
+                    self.link(self.current, next);
                     self.current = next;
                 }
                 None => {
-                    if !self.backtrace.is_empty() {
-                        self.current = self.backtrace[0];
-                        self.backtrace.remove(0);
-                    } else {
-                        break;
+                    match self.backtrace.pop_front() {
+                        Some(previous) => self.current = previous,
+                        None => break
                     }
                 }
             }
-    
-            self.copy_to_map(&mut generator.map);   
         }
     }
 
-    fn copy_to_map(&self, map : &mut Map) {
-        // Clear the map
-        for i in map.tiles.iter_mut() { *i = TileType::Wall; }
-    
-        for cell in self.cells.iter() {
-            let x = cell.column + 1;
-            let y = cell.row + 1;
-            let idx = map.xy_idx(x * 2, y * 2);
-            
-            map.set_tile_at_idx(idx, TileType::Floor);
-            if !cell.walls[TOP] { map.set_tile_at_idx(idx - map.width as usize, TileType::Floor); }
-            if !cell.walls[RIGHT] { map.set_tile_at_idx(idx + 1, TileType::Floor); }
-            if !cell.walls[BOTTOM] { map.set_tile_at_idx(idx + map.width as usize, TileType::Floor); }
-            if !cell.walls[LEFT] { map.set_tile_at_idx(idx - 1, TileType::Floor); }
+    /// Randomised Prim: grow the maze outwards from a random cell, always joining a random
+    /// frontier cell onto the maze.
+    fn generate_prim(&mut self) {
+        let mut in_frontier = vec![false; self.cells.len()];
+        let mut frontier: Vec<usize> = Vec::new();
+
+        let start = self.random_index(self.cells.len());
+        self.cells[start].visited = true;
+        self.add_frontier(start, &mut frontier, &mut in_frontier);
+
+        while !frontier.is_empty() {
+            let pick = self.random_index(frontier.len());
+            let cell = frontier.swap_remove(pick);
+
+            let visited: Vec<usize> = self.neighbours(cell).into_iter().filter(|n| self.cells[*n].visited).collect();
+            let next = visited[self.random_index(visited.len())];
+            self.link(cell, next);
+            self.cells[cell].visited = true;
+            self.add_frontier(cell, &mut frontier, &mut in_frontier);
         }
     }
+
+    fn add_frontier(&self, idx: usize, frontier: &mut Vec<usize>, in_frontier: &mut [bool]) {
+        for n in self.neighbours(idx) {
+            if !self.cells[n].visited && !in_frontier[n] {
+                in_frontier[n] = true;
+                frontier.push(n);
+            }
+        }
+    }
+
+    /// Randomised Kruskal: knock down walls in a random order, unless doing so would join
+    /// two cells that are already connected.
+    fn generate_kruskal(&mut self) {
+        let mut edges = Vec::new();
+        for (idx, cell) in self.cells.iter().enumerate() {
+            if cell.column < self.width - 1 { edges.push((idx, idx + 1)); }
+            if cell.row < self.height - 1 { edges.push((idx, idx + self.width as usize)); }
+        }
+        self.shuffle(&mut edges);
+
+        let mut parents: Vec<usize> = (0..self.cells.len()).collect();
+        for (a, b) in edges {
+            let root_a = find_set(&mut parents, a);
+            let root_b = find_set(&mut parents, b);
+            if root_a != root_b {
+                parents[root_a] = root_b;
+                self.link(a, b);
+            }
+        }
+    }
+
+    /// Eller: works a row at a time, tracking which cells of the current row are already
+    /// connected so each row only needs to be held in memory once.
+    fn generate_eller(&mut self) {
+        let width = self.width as usize;
+        let mut sets: Vec<usize> = (0..width).collect();
+        let mut next_set = width;
+
+        for row in 0..self.height as usize {
+            let last_row = row == self.height as usize - 1;
+
+            // Randomly join neighbours that aren't already connected; the last row joins them all
+            for column in 0..width - 1 {
+                if sets[column] != sets[column + 1] && (last_row || self.coin_flip()) {
+                    let (keep, merge) = (sets[column], sets[column + 1]);
+                    for set in sets.iter_mut() {
+                        if *set == merge { *set = keep; }
+                    }
+                    let idx = row * width + column;
+                    self.link(idx, idx + 1);
+                }
+            }
+
+            if last_row { break; }
+
+            // Every set must carry on into the next row at least once
+            let mut next_sets = vec![usize::MAX; width];
+            for column in 0..width {
+                let set = sets[column];
+                if sets[..column].contains(&set) { continue; }
+
+                let mut members: Vec<usize> = (column..width).filter(|c| sets[*c] == set).collect();
+                self.shuffle(&mut members);
+                let going_down = self.rng.roll_dice(1, members.len() as i32) as usize;
+                for c in members.into_iter().take(going_down) {
+                    let idx = row * width + c;
+                    self.link(idx, idx + width);
+                    next_sets[c] = set;
+                }
+            }
+
+            for set in next_sets.iter_mut() {
+                if *set == usize::MAX {
+                    *set = next_set;
+                    next_set += 1;
+                }
+            }
+            sets = next_sets;
+        }
+    }
+
+    /// Wilson: loop-erased random walks from unvisited cells until they hit the maze.
+    /// Produces an unbiased maze, like Aldous-Broder, but much faster.
+    fn generate_wilson(&mut self) {
+        let mut unvisited: Vec<usize> = (0..self.cells.len()).collect();
+        let mut position_in_path = vec![usize::MAX; self.cells.len()];
+
+        let first = self.random_index(self.cells.len());
+        self.cells[first].visited = true;
+
+        loop {
+            // Lazily drop cells that were added to the maze since we last looked
+            let mut start = None;
+            while !unvisited.is_empty() {
+                let pick = self.random_index(unvisited.len());
+                if self.cells[unvisited[pick]].visited {
+                    unvisited.swap_remove(pick);
+                } else {
+                    start = Some(unvisited[pick]);
+                    break;
+                }
+            }
+            let Some(start) = start else { break };
+
+            let mut path = vec![start];
+            position_in_path[start] = 0;
+            let mut current = start;
+            while !self.cells[current].visited {
+                let neighbours = self.neighbours(current);
+                let next = neighbours[self.random_index(neighbours.len())];
+
+                if position_in_path[next] != usize::MAX {
+                    // We walked into our own path, so erase the loop
+                    for erased in path.drain(position_in_path[next] + 1..) {
+                        position_in_path[erased] = usize::MAX;
+                    }
+                } else {
+                    position_in_path[next] = path.len();
+                    path.push(next);
+                }
+                current = next;
+            }
+
+            for pair in path.windows(2) {
+                self.link(pair[0], pair[1]);
+            }
+            for cell in path {
+                self.cells[cell].visited = true;
+                position_in_path[cell] = usize::MAX;
+            }
+        }
+    }
+
+    /// Aldous-Broder: a plain random walk, linking every cell the first time it is entered.
+    fn generate_aldous_broder(&mut self) {
+        let mut current = self.random_index(self.cells.len());
+        self.cells[current].visited = true;
+        let mut remaining = self.cells.len() - 1;
+
+        while remaining > 0 {
+            let neighbours = self.neighbours(current);
+            let next = neighbours[self.random_index(neighbours.len())];
+            if !self.cells[next].visited {
+                self.link(current, next);
+                self.cells[next].visited = true;
+                remaining -= 1;
+            }
+            current = next;
+        }
+    }
+
+    /// Hunt-and-Kill: random walk until stuck, then hunt for an unvisited cell next to the
+    /// maze and start walking again from there.
+    fn generate_hunt_and_kill(&mut self) {
+        let mut current = self.random_index(self.cells.len());
+        self.cells[current].visited = true;
+        let mut hunt_from = 0;
+
+        loop {
+            // Kill: walk until we run out of unvisited neighbours
+            loop {
+                let unvisited: Vec<usize> = self.neighbours(current).into_iter().filter(|n| !self.cells[*n].visited).collect();
+                if unvisited.is_empty() { break; }
+                let next = unvisited[self.random_index(unvisited.len())];
+                self.link(current, next);
+                self.cells[next].visited = true;
+                current = next;
+            }
+
+            // Hunt: everything before `hunt_from` is known to be visited already
+            while hunt_from < self.cells.len() && self.cells[hunt_from].visited {
+                hunt_from += 1;
+            }
+
+            let mut found = None;
+            for idx in hunt_from..self.cells.len() {
+                if self.cells[idx].visited { continue; }
+                let visited: Vec<usize> = self.neighbours(idx).into_iter().filter(|n| self.cells[*n].visited).collect();
+                if !visited.is_empty() {
+                    let next = visited[self.random_index(visited.len())];
+                    found = Some((idx, next));
+                    break;
+                }
+            }
+
+            match found {
+                Some((idx, next)) => {
+                    self.link(idx, next);
+                    self.cells[idx].visited = true;
+                    current = idx;
+                }
+                None => break
+            }
+        }
+    }
+
+    /// Binary Tree: every cell opens either north or east. Leaves a long open corridor
+    /// along the top and right edges.
+    fn generate_binary_tree(&mut self) {
+        for idx in 0..self.cells.len() {
+            let north = self.calculate_index(self.cells[idx].row - 1, self.cells[idx].column);
+            let east = self.calculate_index(self.cells[idx].row, self.cells[idx].column + 1);
+
+            let next = match (north, east) {
+                (-1, -1) => continue,
+                (n, -1) => n,
+                (-1, e) => e,
+                (n, e) => if self.coin_flip() { n } else { e }
+            };
+            self.link(idx, next as usize);
+        }
+    }
+
+    /// Sidewinder: carve runs along each row, closing every run by opening one of its cells
+    /// north. Like Binary Tree, the top row is a single corridor.
+    fn generate_sidewinder(&mut self) {
+        let width = self.width as usize;
+        for row in 0..self.height as usize {
+            let mut run = Vec::new();
+            for column in 0..width {
+                let idx = row * width + column;
+                run.push(idx);
+
+                let at_east = column == width - 1;
+                let at_north = row == 0;
+                if at_east || (!at_north && self.coin_flip()) {
+                    if !at_north {
+                        let member = run[self.random_index(run.len())];
+                        self.link(member, member - width);
+                    }
+                    run.clear();
+                } else {
+                    self.link(idx, idx + 1);
+                }
+            }
+        }
+    }
+
+    /// Removes roughly `factor` (0.0 - 1.0) of the dead ends by opening them into a
+    /// neighbour, preferring neighbours which are dead ends themselves. This adds loops.
+    fn braid(&mut self, factor: f32) {
+        let mut dead_ends: Vec<usize> = (0..self.cells.len()).filter(|idx| self.is_dead_end(*idx)).collect();
+        self.shuffle(&mut dead_ends);
+
+        for idx in dead_ends {
+            // Braiding a neighbour may already have opened this one up
+            if !self.is_dead_end(idx) { continue; }
+            if self.rng.roll_dice(1, 100) as f32 > factor * 100.0 { continue; }
+
+            let closed: Vec<usize> = self.neighbours(idx).into_iter().filter(|n| self.wall_between(idx, *n)).collect();
+            let dead: Vec<usize> = closed.iter().copied().filter(|n| self.is_dead_end(*n)).collect();
+            let candidates = if dead.is_empty() { closed } else { dead };
+            if candidates.is_empty() { continue; }
+
+            let next = candidates[self.random_index(candidates.len())];
+            self.link(idx, next);
+        }
+    }
+}
+
+/// Union-find lookup with path halving, used by Kruskal's algorithm
+fn find_set(parents: &mut [usize], mut idx: usize) -> usize {
+    while parents[idx] != idx {
+        parents[idx] = parents[parents[idx]];
+        idx = parents[idx];
+    }
+    idx
+}
+
+/// The algorithm used to carve the maze. Each leaves a noticeably different texture.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum MazeAlgorithm {
+    RecursiveBacktracker,
+    Prim,
+    Kruskal,
+    Eller,
+    Wilson,
+    AldousBroder,
+    HuntAndKill,
+    BinaryTree,
+    Sidewinder
 }
 
 pub struct MazeMap {
     pub map: Map,
     pub width: i32,
     pub height: i32,
+    algorithm: MazeAlgorithm,
+    braid: f32
 }
 
 impl MazeMap {
     pub fn new(width: i32, height: i32) -> Self {
+        Self::with_algorithm(width, height, MazeAlgorithm::RecursiveBacktracker)
+    }
+
+    pub fn with_algorithm(width: i32, height: i32, algorithm: MazeAlgorithm) -> Self {
         Self {
             map: Map::new(width, height),
             width,
-            height
+            height,
+            algorithm,
+            braid: 0.0
         }
     }
+
+    /// Removes `factor` (0.0 - 1.0) of the maze's dead ends, turning them into loops
+    pub fn with_braid(mut self, factor: f32) -> Self {
+        self.braid = factor.clamp(0.0, 1.0);
+        self
+    }
 }
 
 impl Architect for MazeMap {
-    fn build(&mut self) {
-        let mut rng = RandomNumberGenerator::new();        
+    fn build_with_rng(&mut self, rng: &mut RandomNumberGenerator) {
+        self.map.clear();
+        let mut maze = Grid::new((self.width / 2) - 2, (self.height / 2) - 2, &mut self.map, rng);
+        match self.algorithm {
+            MazeAlgorithm::RecursiveBacktracker => maze.generate_maze(),
+            MazeAlgorithm::Prim => maze.generate_prim(),
+            MazeAlgorithm::Kruskal => maze.generate_kruskal(),
+            MazeAlgorithm::Eller => maze.generate_eller(),
+            MazeAlgorithm::Wilson => maze.generate_wilson(),
+            MazeAlgorithm::AldousBroder => maze.generate_aldous_broder(),
+            MazeAlgorithm::HuntAndKill => maze.generate_hunt_and_kill(),
+            MazeAlgorithm::BinaryTree => maze.generate_binary_tree(),
+            MazeAlgorithm::Sidewinder => maze.generate_sidewinder()
+        }
 
-        let mut maze = Grid::new((self.width / 2) - 2, (self.height / 2) - 2, &mut rng);
-        maze.generate_maze(self);
+        if self.braid > 0.0 {
+            maze.braid(self.braid);
+        }
 
         // Find a starting point; start at the middle and walk left until we find an open tile
         let start_position = Position::new(2, 2);
@@ -198,12 +584,17 @@ impl Architect for MazeMap {
 
         // Place the stairs
         self.map.set_tile_at_idx(exit_tile, TileType::Exit);
+        self.map.take_snapshot();
     }
 
     fn get_map(&self) -> &Map {
         &self.map
     }
 
+    fn get_map_mut(&mut self) -> &mut Map {
+        &mut self.map
+    }
+
     fn start_position(&self) -> (i32, i32) {
         (self.map.start_position.x, self.map.start_position.y)
     }
@@ -1,4 +1,11 @@
 pub mod utils;
+pub mod hex;
+pub mod batch;
+pub mod stats;
+pub mod validate;
+pub mod transform;
+pub mod symmetry;
+pub mod draw;
 
 pub mod basic;
 pub mod bsp;
@@ -7,31 +14,164 @@ pub mod drunkard_walk;
 pub mod maze;
 pub mod dla;
 pub mod voronoi_cell;
+pub mod composite;
+pub mod random;
 
 use basic::BasicMap;
 use bsp::bsp::BspMap;
 use bsp::bsp_interior::BspInteriorMap;
 use cellular_automata::CellularAutomataMap;
 use drunkard_walk::DrunkardWalkMap;
-use maze::MazeMap;
+use maze::{MazeAlgorithm, MazeMap};
 use dla::DlaMap;
 use voronoi_cell::VoronoiCellMap;
+use composite::CompositeMap;
+use random::{RandomMap, Tag};
 
 use utils::{RandomNumberGenerator, Map};
 
-pub trait Architect {
-    fn build(&mut self);
+pub trait Architect: Send + Sync {
+    /// Builds the map, drawing all randomness from `rng`
+    fn build_with_rng(&mut self, rng: &mut RandomNumberGenerator);
     fn get_map(&self) -> &Map;
+    fn get_map_mut(&mut self) -> &mut Map;
     fn start_position(&self) -> (i32, i32);
+
+    fn build(&mut self) {
+        self.build_with_rng(&mut RandomNumberGenerator::new());
+    }
+
+    /// Builds the map from a fixed seed. The same seed always produces the same map.
+    fn build_with_seed(&mut self, seed: u64) {
+        self.build_with_rng(&mut RandomNumberGenerator::seeded(seed));
+    }
+}
+
+impl Architect for Box<dyn Architect> {
+    fn build_with_rng(&mut self, rng: &mut RandomNumberGenerator) {
+        (**self).build_with_rng(rng)
+    }
+
+    fn get_map(&self) -> &Map {
+        (**self).get_map()
+    }
+
+    fn get_map_mut(&mut self) -> &mut Map {
+        (**self).get_map_mut()
+    }
+
+    fn start_position(&self) -> (i32, i32) {
+        (**self).start_position()
+    }
+}
+
+/// A generator `MapSelector` knows by name, see `MapSelector::GENERATORS`
+#[derive(Clone, Copy)]
+pub struct Generator {
+    /// Name of the selector function that makes it, e.g. `"maze_map_prim"`
+    pub name: &'static str,
+    pub make: fn(i32, i32) -> Box<dyn Architect>,
+    /// The smallest width and height it builds a proper map at. Caves need space for the
+    /// rules to leave more than scraps of floor, and composite maps need room for a region on
+    /// each side.
+    pub min_size: i32,
+    /// What sort of map it makes
+    pub tags: &'static [Tag]
+}
+
+impl Generator {
+    const fn new(name: &'static str, make: fn(i32, i32) -> Box<dyn Architect>, min_size: i32, tags: &'static [Tag]) -> Self {
+        Self { name, make, min_size, tags }
+    }
 }
 
 pub struct MapSelector;
 
 impl MapSelector {
+    /// Every generator available through `by_name`, matching the selector functions. Names,
+    /// minimum sizes and tags are all looked up here.
+    pub const GENERATORS: &'static [Generator] = &[
+        Generator::new("basic_map", |w, h| Box::new(Self::basic_map(w, h)), 10, &[Tag::Rooms]),
+        Generator::new("basic_map_halls", |w, h| Box::new(Self::basic_map_halls(w, h)), 10, &[Tag::Rooms]),
+        Generator::new("basic_map_cells", |w, h| Box::new(Self::basic_map_cells(w, h)), 10, &[Tag::Rooms]),
+        Generator::new("bsp_map", |w, h| Box::new(Self::bsp_map(w, h)), 10, &[Tag::Rooms]),
+        Generator::new("bsp_interior_map", |w, h| Box::new(Self::bsp_interior_map(w, h)), 10, &[Tag::Rooms]),
+        Generator::new("cellular_automata_map", |w, h| Box::new(Self::cellular_automata_map(w, h)), 20, &[Tag::Cave]),
+        Generator::new("cellular_automata_arena_map", |w, h| Box::new(Self::cellular_automata_arena_map(w, h)), 20, &[Tag::Cave, Tag::Symmetric]),
+        Generator::new("cellular_automata_cavernous_map", |w, h| Box::new(Self::cellular_automata_cavernous_map(w, h)), 20, &[Tag::Cave]),
+        Generator::new("cellular_automata_open_map", |w, h| Box::new(Self::cellular_automata_open_map(w, h)), 20, &[Tag::Cave]),
+        Generator::new("cellular_automata_tight_map", |w, h| Box::new(Self::cellular_automata_tight_map(w, h)), 20, &[Tag::Cave]),
+        Generator::new("drunkard_walk_map_open_area", |w, h| Box::new(Self::drunkard_walk_map_open_area(w, h)), 12, &[Tag::Cave]),
+        Generator::new("drunkard_walk_map_open_halls", |w, h| Box::new(Self::drunkard_walk_map_open_halls(w, h)), 12, &[Tag::Cave]),
+        Generator::new("drunkard_walk_map_winding_passages", |w, h| Box::new(Self::drunkard_walk_map_winding_passages(w, h)), 12, &[Tag::Cave]),
+        Generator::new("drunkard_walk_fat_passages", |w, h| Box::new(Self::drunkard_walk_fat_passages(w, h)), 12, &[Tag::Cave]),
+        Generator::new("drunkard_walk_fearful_symmetry", |w, h| Box::new(Self::drunkard_walk_fearful_symmetry(w, h)), 12, &[Tag::Cave, Tag::Symmetric]),
+        Generator::new("drunkard_walk_mines", |w, h| Box::new(Self::drunkard_walk_mines(w, h)), 12, &[Tag::Cave]),
+        Generator::new("drunkard_walk_tunnels_and_chambers", |w, h| Box::new(Self::drunkard_walk_tunnels_and_chambers(w, h)), 12, &[Tag::Cave]),
+        Generator::new("maze_map", |w, h| Box::new(Self::maze_map(w, h)), 10, &[Tag::Maze]),
+        Generator::new("maze_map_prim", |w, h| Box::new(Self::maze_map_prim(w, h)), 10, &[Tag::Maze]),
+        Generator::new("maze_map_kruskal", |w, h| Box::new(Self::maze_map_kruskal(w, h)), 10, &[Tag::Maze]),
+        Generator::new("maze_map_eller", |w, h| Box::new(Self::maze_map_eller(w, h)), 10, &[Tag::Maze]),
+        Generator::new("maze_map_wilson", |w, h| Box::new(Self::maze_map_wilson(w, h)), 10, &[Tag::Maze]),
+        Generator::new("maze_map_aldous_broder", |w, h| Box::new(Self::maze_map_aldous_broder(w, h)), 10, &[Tag::Maze]),
+        Generator::new("maze_map_hunt_and_kill", |w, h| Box::new(Self::maze_map_hunt_and_kill(w, h)), 10, &[Tag::Maze]),
+        Generator::new("maze_map_binary_tree", |w, h| Box::new(Self::maze_map_binary_tree(w, h)), 10, &[Tag::Maze]),
+        Generator::new("maze_map_sidewinder", |w, h| Box::new(Self::maze_map_sidewinder(w, h)), 10, &[Tag::Maze]),
+        Generator::new("maze_map_braided", |w, h| Box::new(Self::maze_map_braided(w, h)), 10, &[Tag::Maze]),
+        Generator::new("dla_map_walk_inwards", |w, h| Box::new(Self::dla_map_walk_inwards(w, h)), 10, &[Tag::Cave]),
+        Generator::new("dla_map_walk_outwards", |w, h| Box::new(Self::dla_map_walk_outwards(w, h)), 10, &[Tag::Cave]),
+        Generator::new("dla_map_central_attractor", |w, h| Box::new(Self::dla_map_central_attractor(w, h)), 10, &[Tag::Cave]),
+        Generator::new("dla_map_insectoid", |w, h| Box::new(Self::dla_map_insectoid(w, h)), 10, &[Tag::Cave, Tag::Symmetric]),
+        Generator::new("dla_map_coral", |w, h| Box::new(Self::dla_map_coral(w, h)), 10, &[Tag::Cave]),
+        Generator::new("voronoi_cell_map_pythagoras", |w, h| Box::new(Self::voronoi_cell_map_pythagoras(w, h)), 16, &[Tag::Cave]),
+        Generator::new("voronoi_cell_map_manhattan", |w, h| Box::new(Self::voronoi_cell_map_manhattan(w, h)), 16, &[Tag::Cave]),
+        Generator::new("voronoi_cell_map_chebyshev", |w, h| Box::new(Self::voronoi_cell_map_chebyshev(w, h)), 16, &[Tag::Cave]),
+        Generator::new("voronoi_cell_map_even", |w, h| Box::new(Self::voronoi_cell_map_even(w, h)), 16, &[Tag::Cave]),
+        Generator::new("hex_cellular_automata_map", |w, h| Box::new(Self::hex_cellular_automata_map(w, h)), 20, &[Tag::Cave, Tag::Hex]),
+        Generator::new("hex_drunkard_walk_map_open_area", |w, h| Box::new(Self::hex_drunkard_walk_map_open_area(w, h)), 12, &[Tag::Cave, Tag::Hex]),
+        Generator::new("hex_dla_map_walk_inwards", |w, h| Box::new(Self::hex_dla_map_walk_inwards(w, h)), 10, &[Tag::Cave, Tag::Hex]),
+        Generator::new("hex_voronoi_cell_map", |w, h| Box::new(Self::hex_voronoi_cell_map(w, h)), 16, &[Tag::Cave, Tag::Hex]),
+        Generator::new("cave_and_fortress_map", |w, h| Box::new(Self::cave_and_fortress_map(w, h)), 24, &[Tag::Cave, Tag::Rooms]),
+        // Falls back on plain rooms when nothing else fits
+        Generator::new("random_map", |w, h| Box::new(Self::random_map(w, h)), 10, &[]),
+    ];
+
+    /// Big enough for every generator in `GENERATORS`; see `min_size` for each one's own
+    pub const MIN_SIZE: i32 = 24;
+
+    /// The generator with this name, or `None` for an unknown name
+    pub fn generator(name: &str) -> Option<&'static Generator> {
+        Self::GENERATORS.iter().find(|g| g.name == name)
+    }
+
+    /// Names of every generator, in the order of `GENERATORS`
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        Self::GENERATORS.iter().map(|g| g.name)
+    }
+
+    /// The smallest width and height a generator builds a proper map at, or `None` for an
+    /// unknown name
+    pub fn min_size(name: &str) -> Option<i32> {
+        Self::generator(name).map(|g| g.min_size)
+    }
+
+    /// What a generator makes, or `None` for an unknown name
+    pub fn tags(name: &str) -> Option<&'static [Tag]> {
+        Self::generator(name).map(|g| g.tags)
+    }
+
     pub fn basic_map(width: i32, height: i32) -> BasicMap {
         BasicMap::new(width, height)
     }
 
+    pub fn basic_map_halls(width: i32, height: i32) -> BasicMap {
+        BasicMap::halls(width, height)
+    }
+
+    pub fn basic_map_cells(width: i32, height: i32) -> BasicMap {
+        BasicMap::cells(width, height)
+    }
+
     pub fn bsp_map(width: i32, height: i32) -> BspMap {
         BspMap::new(width, height)
     }
@@ -44,6 +184,22 @@ impl MapSelector {
         CellularAutomataMap::new(width, height)
     }
 
+    pub fn cellular_automata_arena_map(width: i32, height: i32) -> CellularAutomataMap {
+        CellularAutomataMap::arena(width, height)
+    }
+
+    pub fn cellular_automata_cavernous_map(width: i32, height: i32) -> CellularAutomataMap {
+        CellularAutomataMap::cavernous(width, height)
+    }
+
+    pub fn cellular_automata_open_map(width: i32, height: i32) -> CellularAutomataMap {
+        CellularAutomataMap::open_caves(width, height)
+    }
+
+    pub fn cellular_automata_tight_map(width: i32, height: i32) -> CellularAutomataMap {
+        CellularAutomataMap::tight_caves(width, height)
+    }
+
     pub fn drunkard_walk_map_open_area(width: i32, height: i32) -> DrunkardWalkMap {
         DrunkardWalkMap::open_area(width, height)
     }
@@ -64,10 +220,54 @@ impl MapSelector {
         DrunkardWalkMap::fearful_symmetry(width, height)
     }
 
+    pub fn drunkard_walk_mines(width: i32, height: i32) -> DrunkardWalkMap {
+        DrunkardWalkMap::mines(width, height)
+    }
+
+    pub fn drunkard_walk_tunnels_and_chambers(width: i32, height: i32) -> DrunkardWalkMap {
+        DrunkardWalkMap::tunnels_and_chambers(width, height)
+    }
+
     pub fn maze_map(width: i32, height: i32) -> MazeMap {
         MazeMap::new(width, height)
     }
 
+    pub fn maze_map_prim(width: i32, height: i32) -> MazeMap {
+        MazeMap::with_algorithm(width, height, MazeAlgorithm::Prim)
+    }
+
+    pub fn maze_map_kruskal(width: i32, height: i32) -> MazeMap {
+        MazeMap::with_algorithm(width, height, MazeAlgorithm::Kruskal)
+    }
+
+    pub fn maze_map_eller(width: i32, height: i32) -> MazeMap {
+        MazeMap::with_algorithm(width, height, MazeAlgorithm::Eller)
+    }
+
+    pub fn maze_map_wilson(width: i32, height: i32) -> MazeMap {
+        MazeMap::with_algorithm(width, height, MazeAlgorithm::Wilson)
+    }
+
+    pub fn maze_map_aldous_broder(width: i32, height: i32) -> MazeMap {
+        MazeMap::with_algorithm(width, height, MazeAlgorithm::AldousBroder)
+    }
+
+    pub fn maze_map_hunt_and_kill(width: i32, height: i32) -> MazeMap {
+        MazeMap::with_algorithm(width, height, MazeAlgorithm::HuntAndKill)
+    }
+
+    pub fn maze_map_binary_tree(width: i32, height: i32) -> MazeMap {
+        MazeMap::with_algorithm(width, height, MazeAlgorithm::BinaryTree)
+    }
+
+    pub fn maze_map_sidewinder(width: i32, height: i32) -> MazeMap {
+        MazeMap::with_algorithm(width, height, MazeAlgorithm::Sidewinder)
+    }
+
+    pub fn maze_map_braided(width: i32, height: i32) -> MazeMap {
+        MazeMap::new(width, height).with_braid(0.5)
+    }
+
     pub fn dla_map_walk_inwards(width: i32, height: i32) -> DlaMap {
         DlaMap::walk_inwards(width, height)
     }
@@ -84,6 +284,10 @@ impl MapSelector {
         DlaMap::insectoid(width, height)
     }
 
+    pub fn dla_map_coral(width: i32, height: i32) -> DlaMap {
+        DlaMap::coral(width, height)
+    }
+
     pub fn voronoi_cell_map_pythagoras(width: i32, height: i32) -> VoronoiCellMap {
         VoronoiCellMap::pythagoras(width, height)
     }
@@ -92,28 +296,76 @@ impl MapSelector {
         VoronoiCellMap::manhattan(width, height)
     }
 
-    pub fn random_map(width: i32, height: i32) -> Box<dyn Architect> {
-        let mut rng = RandomNumberGenerator::new();
-
-        let choice = rng.roll_dice(1, 16);
-
-        match choice {
-            1 => Box::new(BspMap::new(width, height)),
-            2 => Box::new(BspInteriorMap::new(width, height)),
-            3 => Box::new(CellularAutomataMap::new(width, height)),
-            4 => Box::new(DrunkardWalkMap::open_area(width, height)),
-            5 => Box::new(DrunkardWalkMap::open_halls(width, height)),
-            6 => Box::new(DrunkardWalkMap::winding_passages(width, height)),
-            7 => Box::new(DrunkardWalkMap::fat_passages(width, height)),
-            8 => Box::new(DrunkardWalkMap::fearful_symmetry(width, height)),
-            9 => Box::new(MazeMap::new(width, height)),
-            10 => Box::new(DlaMap::walk_inwards(width, height)),
-            11 => Box::new(DlaMap::walk_outwards(width, height)),
-            12 => Box::new(DlaMap::central_attractor(width, height)),
-            13 => Box::new(DlaMap::insectoid(width, height)),
-            14 => Box::new(VoronoiCellMap::pythagoras(width, height)),
-            15 => Box::new(VoronoiCellMap::manhattan(width, height)),
-            _ => Box::new(BasicMap::new(width, height)),
-        }
+    pub fn voronoi_cell_map_chebyshev(width: i32, height: i32) -> VoronoiCellMap {
+        VoronoiCellMap::chebyshev(width, height)
+    }
+
+    pub fn voronoi_cell_map_even(width: i32, height: i32) -> VoronoiCellMap {
+        VoronoiCellMap::even_cells(width, height)
+    }
+
+    pub fn hex_cellular_automata_map(width: i32, height: i32) -> CellularAutomataMap {
+        CellularAutomataMap::new(width, height).on_hex_grid()
+    }
+
+    pub fn hex_drunkard_walk_map_open_area(width: i32, height: i32) -> DrunkardWalkMap {
+        DrunkardWalkMap::open_area(width, height).on_hex_grid()
+    }
+
+    pub fn hex_dla_map_walk_inwards(width: i32, height: i32) -> DlaMap {
+        DlaMap::walk_inwards(width, height).on_hex_grid()
+    }
+
+    pub fn hex_voronoi_cell_map(width: i32, height: i32) -> VoronoiCellMap {
+        VoronoiCellMap::pythagoras(width, height).on_hex_grid()
+    }
+
+    pub fn cave_and_fortress_map(width: i32, height: i32) -> CompositeMap {
+        CompositeMap::cave_and_fortress(width, height)
+    }
+
+    /// Looks a generator up by the name of its selector function, e.g. `"maze_map_prim"`
+    pub fn by_name(name: &str, width: i32, height: i32) -> Option<Box<dyn Architect>> {
+        Self::generator(name).map(|g| (g.make)(width, height))
+    }
+
+    /// The drunkard's walk generator with this name, for callers which want to change its
+    /// settings before building
+    pub fn drunkard_by_name(name: &str, width: i32, height: i32) -> Option<DrunkardWalkMap> {
+        let architect = match name {
+            "drunkard_walk_map_open_area" => Self::drunkard_walk_map_open_area(width, height),
+            "drunkard_walk_map_open_halls" => Self::drunkard_walk_map_open_halls(width, height),
+            "drunkard_walk_map_winding_passages" => Self::drunkard_walk_map_winding_passages(width, height),
+            "drunkard_walk_fat_passages" => Self::drunkard_walk_fat_passages(width, height),
+            "drunkard_walk_fearful_symmetry" => Self::drunkard_walk_fearful_symmetry(width, height),
+            "drunkard_walk_mines" => Self::drunkard_walk_mines(width, height),
+            "drunkard_walk_tunnels_and_chambers" => Self::drunkard_walk_tunnels_and_chambers(width, height),
+            "hex_drunkard_walk_map_open_area" => Self::hex_drunkard_walk_map_open_area(width, height),
+            _ => return None
+        };
+
+        Some(architect)
+    }
+
+    /// The DLA generator with this name, for callers which want to change its settings
+    /// before building
+    pub fn dla_by_name(name: &str, width: i32, height: i32) -> Option<DlaMap> {
+        let architect = match name {
+            "dla_map_walk_inwards" => Self::dla_map_walk_inwards(width, height),
+            "dla_map_walk_outwards" => Self::dla_map_walk_outwards(width, height),
+            "dla_map_central_attractor" => Self::dla_map_central_attractor(width, height),
+            "dla_map_insectoid" => Self::dla_map_insectoid(width, height),
+            "dla_map_coral" => Self::dla_map_coral(width, height),
+            "hex_dla_map_walk_inwards" => Self::hex_dla_map_walk_inwards(width, height),
+            _ => return None
+        };
+
+        Some(architect)
+    }
+
+    /// Any square grid generator, all equally likely. The pick comes from the build's seed,
+    /// and is kept in `choice`. See `RandomSelector` to weight or filter the choice.
+    pub fn random_map(width: i32, height: i32) -> RandomMap {
+        RandomMap::new(width, height)
     }
 }
\ No newline at end of file
@@ -0,0 +1,193 @@
+//! Weighted random choice between generators
+//!
+//! Generators are registered by their `MapSelector` name with a weight, and can be filtered
+//! by tag or by the smallest map they cope with. Every pick comes back with the name and seed
+//! it used, so the same map can be built again later. Names `MapSelector` doesn't know are
+//! refused with `None` wherever they're given, and never picked.
+
+use super::basic::BasicMap;
+use super::utils::{Map, RandomNumberGenerator};
+use super::{Architect, Generator, MapSelector};
+
+/// What sort of map a generator makes, see `Generator::tags`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Tag {
+    Cave,
+    Rooms,
+    Maze,
+    Symmetric,
+    /// Built on a hex grid
+    Hex
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RandomEntry {
+    /// Name of the `MapSelector` generator
+    pub name: String,
+    /// Chance of being picked, relative to the other entries
+    pub weight: u32,
+    pub tags: Vec<Tag>,
+    /// Not picked for maps narrower or shorter than this
+    pub min_size: i32
+}
+
+impl RandomEntry {
+    /// An entry with the generator's usual tags and minimum size, or `None` for an unknown name
+    pub fn new(name: &str, weight: u32) -> Option<Self> {
+        MapSelector::generator(name).map(|g| Self::from_generator(g, weight))
+    }
+
+    fn from_generator(generator: &Generator, weight: u32) -> Self {
+        Self { name: generator.name.to_string(), weight, tags: generator.tags.to_vec(), min_size: generator.min_size }
+    }
+}
+
+/// What `RandomSelector` picked. Building it again gives the same map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RandomChoice {
+    pub name: String,
+    pub seed: u64
+}
+
+impl RandomChoice {
+    /// Builds the chosen generator with the chosen seed, or returns `None` for an unknown name
+    pub fn build(&self, width: i32, height: i32) -> Option<Box<dyn Architect>> {
+        let mut architect = MapSelector::by_name(&self.name, width, height)?;
+        architect.build_with_seed(self.seed);
+        Some(architect)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RandomSelector {
+    pub entries: Vec<RandomEntry>
+}
+
+impl RandomSelector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every named generator, equally likely
+    pub fn all() -> Self {
+        let entries = MapSelector::GENERATORS.iter()
+            .filter(|g| g.name != "random_map")
+            .map(|g| RandomEntry::from_generator(g, 1))
+            .collect();
+        Self { entries }
+    }
+
+    /// Adds a generator with its usual tags, e.g. `with_generator("bsp_map", 30)`, or returns
+    /// `None` for an unknown name
+    pub fn with_generator(self, name: &str, weight: u32) -> Option<Self> {
+        Some(self.with_entry(RandomEntry::new(name, weight)?))
+    }
+
+    pub fn with_entry(mut self, entry: RandomEntry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    /// Only the entries with `tag`
+    pub fn tagged(&self, tag: Tag) -> Self {
+        Self { entries: self.entries.iter().filter(|e| e.tags.contains(&tag)).cloned().collect() }
+    }
+
+    /// Only the entries without `tag`
+    pub fn without(&self, tag: Tag) -> Self {
+        Self { entries: self.entries.iter().filter(|e| !e.tags.contains(&tag)).cloned().collect() }
+    }
+
+    /// Only the entries which cope with a map this size, leaving out any with unknown names
+    pub fn fitting(&self, width: i32, height: i32) -> Self {
+        let entries = self.entries.iter()
+            .filter(|e| width >= e.min_size && height >= e.min_size && MapSelector::min_size(&e.name).is_some())
+            .cloned()
+            .collect();
+        Self { entries }
+    }
+
+    /// Picks a generator for a map of this size, by weight, along with a seed to build it
+    /// from. Returns `None` if nothing known fits or every weight is 0.
+    pub fn choose(&self, width: i32, height: i32, rng: &mut RandomNumberGenerator) -> Option<RandomChoice> {
+        let candidates = self.fitting(width, height).entries;
+        let total: u64 = candidates.iter().map(|e| e.weight as u64).sum();
+        if total == 0 { return None; }
+
+        let mut roll = rng.next_u64() % total;
+        let entry = candidates.iter()
+            .find(|e| {
+                if roll < e.weight as u64 { return true; }
+                roll -= e.weight as u64;
+                false
+            })
+            .unwrap();
+        Some(RandomChoice { name: entry.name.clone(), seed: rng.next_u64() })
+    }
+
+    /// Picks a generator and builds it, returning what was picked alongside the map
+    pub fn generate(&self, width: i32, height: i32, rng: &mut RandomNumberGenerator) -> Option<(RandomChoice, Box<dyn Architect>)> {
+        let choice = self.choose(width, height, rng)?;
+        let architect = choice.build(width, height)?;
+        Some((choice, architect))
+    }
+}
+
+/// Builds whichever generator `selector` picks, choosing with the build's own randomness, so
+/// the same seed always picks the same generator and builds the same map
+pub struct RandomMap {
+    pub map: Map,
+    pub width: i32,
+    pub height: i32,
+    pub selector: RandomSelector,
+    /// What the last build picked
+    pub choice: Option<RandomChoice>
+}
+
+impl RandomMap {
+    pub fn with_selector(width: i32, height: i32, selector: RandomSelector) -> Self {
+        Self {
+            map: Map::new(width, height),
+            width,
+            height,
+            selector,
+            choice: None
+        }
+    }
+
+    /// Any square grid generator, all equally likely
+    pub fn new(width: i32, height: i32) -> Self {
+        Self::with_selector(width, height, RandomSelector::all().without(Tag::Hex))
+    }
+}
+
+impl Architect for RandomMap {
+    fn build_with_rng(&mut self, rng: &mut RandomNumberGenerator) {
+        // Nothing known fits, or nothing has any weight: fall back on plain rooms
+        let choice = self.selector.choose(self.width, self.height, rng)
+            .unwrap_or_else(|| RandomChoice { name: "basic_map".to_string(), seed: rng.next_u64() });
+
+        // `choose` only picks names `by_name` knows
+        let mut architect: Box<dyn Architect> = MapSelector::by_name(&choice.name, self.width, self.height)
+            .unwrap_or_else(|| Box::new(BasicMap::new(self.width, self.height)));
+        if self.map.history.is_some() {
+            architect.get_map_mut().record_history();
+        }
+        architect.build_with_seed(choice.seed);
+
+        self.map = architect.get_map().clone();
+        self.choice = Some(choice);
+    }
+
+    fn get_map(&self) -> &Map {
+        &self.map
+    }
+
+    fn get_map_mut(&mut self) -> &mut Map {
+        &mut self.map
+    }
+
+    fn start_position(&self) -> (i32, i32) {
+        (self.map.start_position.x, self.map.start_position.y)
+    }
+}
@@ -0,0 +1,69 @@
+//! Summary statistics for a generated map
+
+use bracket_pathfinding::prelude::*;
+
+use super::utils::{Map, TileType};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MapStats {
+    /// Fraction of all tiles which can be walked on
+    pub floor_ratio: f32,
+    /// Number of separate walkable areas
+    pub region_count: usize,
+    /// Walking distance from the start to the nearest exit, if one can be reached
+    pub start_exit_distance: Option<f32>
+}
+
+impl MapStats {
+    pub fn from_map(map: &Map) -> Self {
+        let walkable = map.tiles.iter().filter(|t| is_walkable(**t)).count();
+
+        let start_idx = map.xy_idx(map.start_position.x, map.start_position.y);
+        let dijkstra_map = DijkstraMap::new(map.width as usize, map.height as usize, &[start_idx], map, map.tiles.len() as f32);
+        let start_exit_distance = map.tiles.iter()
+            .enumerate()
+            .filter(|(idx, tile)| **tile == TileType::Exit && dijkstra_map.map[*idx] < f32::MAX)
+            .map(|(idx, _)| dijkstra_map.map[idx])
+            .reduce(f32::min);
+
+        Self {
+            floor_ratio: walkable as f32 / map.tiles.len() as f32,
+            region_count: walkable_regions(map).len(),
+            start_exit_distance
+        }
+    }
+}
+
+pub(crate) fn is_walkable(tile: TileType) -> bool {
+    tile == TileType::Floor || tile == TileType::Exit
+}
+
+/// Groups the walkable tiles into connected areas, returning the tile indices of each area.
+/// Tiles connect in the same directions a path can take.
+pub fn walkable_regions(map: &Map) -> Vec<Vec<usize>> {
+    let mut regions = Vec::new();
+    let mut seen = vec![false; map.tiles.len()];
+
+    for first in 0..map.tiles.len() {
+        if seen[first] || !is_walkable(map.tiles[first]) { continue; }
+
+        let mut region = Vec::new();
+        let mut open = vec![first];
+        seen[first] = true;
+        while let Some(idx) = open.pop() {
+            region.push(idx);
+            let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+            for p in map.adjacent(x, y) {
+                let next = map.xy_idx(p.x, p.y);
+                if !seen[next] && is_walkable(map.tiles[next]) {
+                    seen[next] = true;
+                    open.push(next);
+                }
+            }
+        }
+
+        regions.push(region);
+    }
+
+    regions
+}
@@ -0,0 +1,82 @@
+//! Map symmetry
+//!
+//! A `Symmetry` maps each tile to the set of tiles which must match it. Generators paint
+//! every tile together with all of its images, so the finished map is symmetric as long as
+//! the image sets are exact. The mirror and two-fold modes are exact on any map; four-fold
+//! rotation is only exact on square maps, and radial symmetry rounds its images to the grid,
+//! so it is only exact for one, two or four folds on a square map. Symmetry is worked out on
+//! offset coordinates, so hex maps only come out roughly symmetric.
+
+use std::f32::consts::TAU;
+
+use super::stats::is_walkable;
+use super::utils::{Map, Position};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Symmetry {
+    None,
+    /// Mirrored left to right
+    Horizontal,
+    /// Mirrored top to bottom
+    Vertical,
+    /// Mirrored both ways, so all four quadrants match
+    Both,
+    /// Unchanged by half a turn about the centre
+    Rotational2,
+    /// Unchanged by a quarter turn about the centre
+    Rotational4,
+    /// The given number of copies spaced evenly around the centre
+    Radial(u32)
+}
+
+impl Symmetry {
+    /// Every in-bounds position which has to match `(x, y)` in a `width` by `height` map,
+    /// starting with `(x, y)` itself and with no repeats.
+    pub fn images(self, width: i32, height: i32, x: i32, y: i32) -> Vec<Position> {
+        let (mx, my) = (width - 1 - x, height - 1 - y);
+        let candidates = match self {
+            Symmetry::None => vec![(x, y)],
+            Symmetry::Horizontal => vec![(x, y), (mx, y)],
+            Symmetry::Vertical => vec![(x, y), (x, my)],
+            Symmetry::Both => vec![(x, y), (mx, y), (x, my), (mx, my)],
+            Symmetry::Rotational2 => vec![(x, y), (mx, my)],
+            Symmetry::Rotational4 => rotations(width, height, x, y, 4),
+            Symmetry::Radial(folds) => rotations(width, height, x, y, u32::max(folds, 1))
+        };
+
+        let mut images: Vec<Position> = Vec::with_capacity(candidates.len());
+        for (ix, iy) in candidates {
+            let image = Position::new(ix, iy);
+            let in_bounds = ix >= 0 && ix < width && iy >= 0 && iy < height;
+            if in_bounds && !images.contains(&image) {
+                images.push(image);
+            }
+        }
+        images
+    }
+
+    /// True if every tile is walkable exactly when all of its images are. Exits count as
+    /// floor, since a map only has the one.
+    pub fn is_symmetric(self, map: &Map) -> bool {
+        (0..map.height).all(|y| (0..map.width).all(|x| {
+            let walkable = is_walkable(map.get_tile(x, y));
+            self.images(map.width, map.height, x, y)
+                .iter()
+                .all(|p| is_walkable(map.get_tile(p.x, p.y)) == walkable)
+        }))
+    }
+}
+
+/// `(x, y)` turned about the centre of the map by each of `folds` even steps
+fn rotations(width: i32, height: i32, x: i32, y: i32, folds: u32) -> Vec<(i32, i32)> {
+    let cx = (width - 1) as f32 / 2.0;
+    let cy = (height - 1) as f32 / 2.0;
+    let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+
+    (0..folds).map(|k| {
+        let (sin, cos) = (TAU * k as f32 / folds as f32).sin_cos();
+        let rx = cx + dx * cos - dy * sin;
+        let ry = cy + dx * sin + dy * cos;
+        (rx.round() as i32, ry.round() as i32)
+    }).collect()
+}
@@ -0,0 +1,136 @@
+//! Geometric transforms
+//!
+//! Rotating, flipping, cropping, padding and pasting maps. The start position and rooms are
+//! moved along with the tiles; exits are ordinary tiles, so they come along for free. Rooms
+//! which end up partly off the map are clipped to it, and ones which end up entirely off it
+//! are dropped. The start is moved but never clipped, so check `validate` after cropping.
+//! Sizes below zero are taken as zero, so over-cropping leaves an empty map. History
+//! snapshots are in the old shape, so they're not carried over; a map recording history
+//! gives one that records too, starting empty.
+//!
+//! Odd-r hex maps can't be rotated or flipped without changing which tiles are neighbours,
+//! so those transforms return `None` for hex maps. Cropping, padding or pasting a hex map by an
+//! odd number of rows changes which rows are shifted, so keep those offsets even.
+
+use super::utils::{GridType, Map, Position, Room, TileType};
+
+/// Clockwise rotation
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rotation {
+    Quarter,
+    Half,
+    ThreeQuarters
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Flip {
+    /// Mirror left to right
+    Horizontal,
+    /// Mirror top to bottom
+    Vertical
+}
+
+impl Map {
+    /// Builds a `width` by `height` map filled with `fill`, then moves every tile, the start
+    /// and the rooms through `to`. Anything that lands off the new map is left out.
+    fn transformed<F>(&self, width: i32, height: i32, fill: TileType, to: F) -> Map
+    where
+        F: Fn(i32, i32) -> (i32, i32)
+    {
+        let (width, height) = (i32::max(0, width), i32::max(0, height));
+        let mut map = Map::new(width, height);
+        map.grid = self.grid;
+        if self.history.is_some() {
+            map.record_history();
+        }
+        map.tiles.fill(fill);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (nx, ny) = to(x, y);
+                if map.in_bounds(nx, ny) {
+                    map.set_tile(nx, ny, self.get_tile(x, y));
+                }
+            }
+        }
+
+        let (sx, sy) = to(self.start_position.x, self.start_position.y);
+        map.start_position = Position::new(sx, sy);
+
+        // Room floors run from `x1 + 1` to `x2`, so move the floor's corners rather than the
+        // room's, or flips and rotations would shift the room off its floor
+        map.rooms = self.rooms.iter().filter_map(|room| {
+            let (ax, ay) = to(room.x1 + 1, room.y1 + 1);
+            let (bx, by) = to(room.x2, room.y2);
+            clip_room(ax.min(bx) - 1, ay.min(by) - 1, ax.max(bx), ay.max(by), width, height)
+        }).collect();
+
+        map
+    }
+
+    /// The map turned clockwise, or `None` for a hex map
+    pub fn rotated(&self, rotation: Rotation) -> Option<Map> {
+        if self.grid != GridType::Square { return None; }
+        let (w, h) = (self.width, self.height);
+        Some(match rotation {
+            Rotation::Quarter => self.transformed(h, w, TileType::Wall, |x, y| (h - 1 - y, x)),
+            Rotation::Half => self.transformed(w, h, TileType::Wall, |x, y| (w - 1 - x, h - 1 - y)),
+            Rotation::ThreeQuarters => self.transformed(h, w, TileType::Wall, |x, y| (y, w - 1 - x))
+        })
+    }
+
+    /// The map mirrored, or `None` for a hex map
+    pub fn flipped(&self, flip: Flip) -> Option<Map> {
+        if self.grid != GridType::Square { return None; }
+        let (w, h) = (self.width, self.height);
+        Some(match flip {
+            Flip::Horizontal => self.transformed(w, h, TileType::Wall, |x, y| (w - 1 - x, y)),
+            Flip::Vertical => self.transformed(w, h, TileType::Wall, |x, y| (x, h - 1 - y))
+        })
+    }
+
+    /// The `width` by `height` area with its top left corner at `(x, y)`. Any of it that
+    /// hangs off this map comes back as wall.
+    pub fn cropped(&self, x: i32, y: i32, width: i32, height: i32) -> Map {
+        self.transformed(width, height, TileType::Wall, |tx, ty| (tx - x, ty - y))
+    }
+
+    /// Adds a border of `fill` tiles around the map. A negative border trims that edge instead.
+    pub fn padded(&self, left: i32, top: i32, right: i32, bottom: i32, fill: TileType) -> Map {
+        let width = self.width.saturating_add(left).saturating_add(right);
+        let height = self.height.saturating_add(top).saturating_add(bottom);
+        self.transformed(width, height, fill, |x, y| (x + left, y + top))
+    }
+
+    /// Grows or shrinks the map from its bottom and right edges, filling new space with `fill`
+    pub fn resized(&self, width: i32, height: i32, fill: TileType) -> Map {
+        self.transformed(width, height, fill, |x, y| (x, y))
+    }
+
+    /// Pastes `source` into this map with its top left corner at `(x, y)`. `Void` tiles in
+    /// the source are treated as transparent, and anything off the edge is clipped. The
+    /// source's rooms are added to this map's; its start position is ignored. Exits are
+    /// copied like any other tile, so clear them from the source first if they aren't wanted.
+    pub fn blit(&mut self, source: &Map, x: i32, y: i32) {
+        for sy in 0..source.height {
+            for sx in 0..source.width {
+                let tile = source.get_tile(sx, sy);
+                if tile != TileType::Void && self.in_bounds(x + sx, y + sy) {
+                    self.set_tile(x + sx, y + sy, tile);
+                }
+            }
+        }
+
+        for room in source.rooms.iter() {
+            if let Some(room) = clip_room(room.x1 + x, room.y1 + y, room.x2 + x, room.y2 + y, self.width, self.height) {
+                self.rooms.push(room);
+            }
+        }
+    }
+}
+
+fn clip_room(x1: i32, y1: i32, x2: i32, y2: i32, width: i32, height: i32) -> Option<Room> {
+    let (x1, y1) = (x1.max(0), y1.max(0));
+    let (x2, y2) = (x2.min(width - 1), y2.min(height - 1));
+    (x1 <= x2 && y1 <= y2).then_some(Room { x1, y1, x2, y2 })
+}
@@ -1,4 +1,7 @@
-use rand::Rng;
+use std::collections::VecDeque;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 #[cfg(feature = "debug")]
 use std::fs;
@@ -8,7 +11,12 @@ use std::io::prelude::*;
 
 use bracket_pathfinding::prelude::*;
 
-#[derive(Copy, Clone)]
+use super::draw::{line_points, Brush};
+use super::hex::{hex_distance, hex_field_of_view, hex_step};
+use super::stats::{is_walkable, walkable_regions};
+pub use super::symmetry::Symmetry;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Position {
     pub x: i32,
     pub y: i32
@@ -28,7 +36,7 @@ pub enum TileType {
     Void
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Room {
     pub x1 : i32,
     pub x2 : i32,
@@ -51,11 +59,25 @@ impl Room {
     }
 }
 
+/// The shape of the cells making up a map
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GridType {
+    Square,
+    /// Pointy-topped hexes in "odd-r" offset layout (see `maps::hex`)
+    Hex
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Map {
     pub tiles: Vec<TileType>,
     pub width: i32,
     pub height: i32,
     pub start_position: Position,
+    pub grid: GridType,
+    /// Rooms carved by room based generators; empty for the others
+    pub rooms: Vec<Room>,
+    /// Copies of `tiles` taken as the map was generated, if recording was switched on
+    pub history: Option<Vec<Vec<TileType>>>,
 }
 
 impl Map {
@@ -64,7 +86,40 @@ impl Map {
             tiles: vec![TileType::Wall; (width * height) as usize],
             width,
             height,
-            start_position: Position::new(0, 0)
+            start_position: Position::new(0, 0),
+            grid: GridType::Square,
+            rooms: Vec::new(),
+            history: None
+        }
+    }
+
+    pub fn new_hex(width: i32, height: i32) -> Self {
+        Self {
+            grid: GridType::Hex,
+            ..Self::new(width, height)
+        }
+    }
+
+    /// Walls over every tile and forgets the rooms, the start and any snapshots, ready for
+    /// another build. The grid stays the same, and so does whether history is recorded.
+    pub fn clear(&mut self) {
+        self.tiles.fill(TileType::Wall);
+        self.rooms.clear();
+        self.start_position = Position::new(0, 0);
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+    }
+
+    /// Starts keeping a snapshot of the tiles at each step of generation
+    pub fn record_history(&mut self) {
+        self.history = Some(Vec::new());
+    }
+
+    /// Records the current tiles, if history is being recorded
+    pub fn take_snapshot(&mut self) {
+        if let Some(history) = &mut self.history {
+            history.push(self.tiles.clone());
         }
     }
 
@@ -94,6 +149,81 @@ impl Map {
         self.tiles.iter().filter(|a| **a == tile).count()
     }
 
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && x < self.width && y >= 0 && y < self.height
+    }
+
+    /// Number of directions a walker can step in: 4 on a square grid, 6 on a hex grid
+    pub fn direction_count(&self) -> i32 {
+        match self.grid {
+            GridType::Square => 4,
+            GridType::Hex => 6
+        }
+    }
+
+    /// Position reached by taking one step in `direction` (0..direction_count).
+    /// The result may be out of bounds.
+    pub fn step(&self, x: i32, y: i32, direction: i32) -> (i32, i32) {
+        match self.grid {
+            GridType::Square => match direction {
+                0 => (x - 1, y),
+                1 => (x + 1, y),
+                2 => (x, y - 1),
+                _ => (x, y + 1)
+            },
+            GridType::Hex => hex_step(x, y, direction as usize)
+        }
+    }
+
+    /// In-bounds tiles reachable in a single step (cardinal directions on a square grid)
+    pub fn adjacent(&self, x: i32, y: i32) -> SmallVec<[Position; 8]> {
+        (0..self.direction_count())
+            .map(|d| self.step(x, y, d))
+            .filter(|(nx, ny)| self.in_bounds(*nx, *ny))
+            .map(|(nx, ny)| Position::new(nx, ny))
+            .collect()
+    }
+
+    /// In-bounds tiles touching this one, including diagonals on a square grid
+    pub fn surrounding(&self, x: i32, y: i32) -> SmallVec<[Position; 8]> {
+        match self.grid {
+            GridType::Square => {
+                let mut result = SmallVec::new();
+                for dy in -1 ..= 1 {
+                    for dx in -1 ..= 1 {
+                        if (dx != 0 || dy != 0) && self.in_bounds(x + dx, y + dy) {
+                            result.push(Position::new(x + dx, y + dy));
+                        }
+                    }
+                }
+                result
+            }
+            GridType::Hex => self.adjacent(x, y)
+        }
+    }
+
+    /// Distance between two positions, measured in the map's own geometry
+    pub fn distance(&self, a: Position, b: Position) -> f32 {
+        match self.grid {
+            GridType::Square => DistanceAlg::Pythagoras.distance2d(
+                Point::new(a.x, a.y),
+                Point::new(b.x, b.y)
+            ),
+            GridType::Hex => hex_distance(a, b) as f32
+        }
+    }
+
+    /// Tiles visible from `origin` within `radius`, treating walls as opaque
+    pub fn field_of_view(&self, origin: Position, radius: i32) -> Vec<Position> {
+        match self.grid {
+            GridType::Square => field_of_view(Point::new(origin.x, origin.y), radius, self)
+                .into_iter()
+                .map(|p| Position::new(p.x, p.y))
+                .collect(),
+            GridType::Hex => hex_field_of_view(self, origin, radius)
+        }
+    }
+
     fn is_exit_valid(&self, x: i32, y: i32) -> bool {
         if x < 1 || x > self.width-1 || y < 1 || y > self.height-1 { return false; }
         self.get_tile(x, y) != TileType::Wall
@@ -117,32 +247,44 @@ impl BaseMap for Map {
         let mut exits = SmallVec::new();
         let x = idx as i32 % self.width;
         let y = idx as i32 / self.width;
-        let w = self.width as usize;
-    
-        // Cardinal directions
-        if self.is_exit_valid(x - 1, y) { exits.push((idx - 1, 1.0)) };
-        if self.is_exit_valid(x + 1, y) { exits.push((idx + 1, 1.0)) };
-        if self.is_exit_valid(x, y - 1) { exits.push((idx - w, 1.0)) };
-        if self.is_exit_valid(x, y + 1) { exits.push((idx + w, 1.0)) };
-    
+
+        // Cardinal directions on a square grid, all six sides on a hex grid
+        for direction in 0..self.direction_count() {
+            let (nx, ny) = self.step(x, y, direction);
+            if self.is_exit_valid(nx, ny) { exits.push((self.xy_idx(nx, ny), 1.0)) };
+        }
+
         exits
     }
 
     fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
         let w = self.width as usize;
-        let p1 = Point::new(idx1 % w, idx1 / w);
-        let p2 = Point::new(idx2 % w, idx2 / w);
-        DistanceAlg::Pythagoras.distance2d(p1, p2)
+        let p1 = Position::new((idx1 % w) as i32, (idx1 / w) as i32);
+        let p2 = Position::new((idx2 % w) as i32, (idx2 / w) as i32);
+        self.distance(p1, p2)
     }
 }
 
+/// Seeded maps are meant to come out the same on every release, so this wraps a named
+/// algorithm rather than `StdRng`, which `rand` is free to change
 pub struct RandomNumberGenerator {
-    rng: rand::rngs::ThreadRng
+    rng: ChaCha8Rng
+}
+
+impl Default for RandomNumberGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl RandomNumberGenerator {
     pub fn new() -> Self {
-        Self { rng: rand::thread_rng() }
+        Self { rng: ChaCha8Rng::from_entropy() }
+    }
+
+    /// A generator which always produces the same sequence for the same seed
+    pub fn seeded(seed: u64) -> Self {
+        Self { rng: ChaCha8Rng::seed_from_u64(seed) }
     }
     
     pub fn range(&mut self, min: i32, max: i32) -> i32 {
@@ -152,6 +294,10 @@ impl RandomNumberGenerator {
     pub fn roll_dice(&mut self, start: i32, end: i32) -> i32 {
         self.rng.gen_range(start..end+1)
     }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.rng.gen()
+    }
 }
 
 #[cfg(feature = "debug")]
@@ -160,6 +306,8 @@ pub fn output_map(map: &Map, name: &str) {
     
     for y in 0..map.height {
         let mut inner = Vec::new();
+        // Hex rows are interleaved so odd rows sit between the tiles above them
+        if map.grid == GridType::Hex && y % 2 == 1 { inner.push(' '); }
         for x in 0..map.width {
             let tile = map.get_tile(x, y);
             match tile {
@@ -168,6 +316,7 @@ pub fn output_map(map: &Map, name: &str) {
                 TileType::Exit => inner.push('E'),
                 _ => {}
             }
+            if map.grid == GridType::Hex { inner.push(' '); }
         }
 
         outer.push(inner);
@@ -181,20 +330,25 @@ pub fn output_map(map: &Map, name: &str) {
         for c in inner.iter() {
             write!(output, "{}", c).unwrap();
         }
-        write!(output, "\n").unwrap();
+        writeln!(output).unwrap();
     }
 }
 
 /// Searches a map, removes unreachable areas and returns the most distant tile.
 pub(crate) fn remove_unreachable_areas_returning_most_distant(map : &mut Map, start_idx : usize) -> usize {
     let map_starts : Vec<usize> = vec![start_idx];
-    let dijkstra_map = DijkstraMap::new(map.width as usize, map.height as usize, &map_starts , map, 200.0);
-    let mut exit_tile = (0, 0.0_f32);
+    // Tiles beyond the search depth count as unreachable and get walled off, so a fixed cap
+    // of 200 used to cut the far ends off big mazes and caves. No path can be longer than
+    // the number of tiles.
+    let max_depth = map.tiles.len() as f32;
+    let dijkstra_map = DijkstraMap::new(map.width as usize, map.height as usize, &map_starts , map, max_depth);
+    // With nowhere else to go, the exit is the start itself
+    let mut exit_tile = (start_idx, 0.0_f32);
     for (i, tile) in map.tiles.iter_mut().enumerate() {
         if *tile == TileType::Floor {
             let distance_to_start = dijkstra_map.map[i];
             // We can't get to this tile - so we'll make it a wall
-            if distance_to_start == std::f32::MAX {
+            if distance_to_start == f32::MAX {
                 *tile = TileType::Wall;
             } else {
                 // If it is further away than our current exit candidate, move the exit
@@ -209,62 +363,108 @@ pub(crate) fn remove_unreachable_areas_returning_most_distant(map : &mut Map, st
     exit_tile.0
 }
 
-#[allow(dead_code)]
-#[derive(PartialEq, Copy, Clone)]
-pub(crate) enum Symmetry { None, Horizontal, Vertical, Both }
-
-pub(crate) fn paint(map: &mut Map, mode: Symmetry, brush_size: i32, x: i32, y:i32) {
-    match mode {
-        Symmetry::None => apply_paint(map, brush_size, x, y),
-        Symmetry::Horizontal => {
-            let center_x = map.width / 2;
-            if x == center_x {
-                apply_paint(map, brush_size, x, y);                    
-            } else {
-                let dist_x = i32::abs(center_x - x);
-                apply_paint(map, brush_size, center_x + dist_x, y);
-                apply_paint(map, brush_size, center_x - dist_x, y);
+/// Joins every walkable area to the one holding the start, carving a corridor from each
+/// along the shortest route to anything already connected. Returns the tiles carved.
+pub(crate) fn connect_walkable_areas(map: &mut Map, start_idx: usize) -> Vec<usize> {
+    let mut carved = Vec::new();
+    let mut connected = vec![false; map.tiles.len()];
+    let mut areas = walkable_regions(map);
+    if let Some(main) = areas.iter().position(|a| a.contains(&start_idx)) {
+        for idx in areas.swap_remove(main) { connected[idx] = true; }
+    }
+
+    for area in areas {
+        if connected[area[0]] { continue; }
+
+        // Breadth first search out from the whole area, through walls, staying off the border
+        let mut came_from = vec![usize::MAX; map.tiles.len()];
+        let mut open: VecDeque<usize> = area.iter().copied().collect();
+        for idx in area.iter() { came_from[*idx] = *idx; }
+
+        let mut reached = None;
+        while let Some(idx) = open.pop_front() {
+            if connected[idx] { reached = Some(idx); break; }
+            let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+            for p in map.adjacent(x, y) {
+                if p.x < 1 || p.y < 1 || p.x > map.width - 2 || p.y > map.height - 2 { continue; }
+                let next = map.xy_idx(p.x, p.y);
+                if came_from[next] == usize::MAX {
+                    came_from[next] = idx;
+                    open.push_back(next);
+                }
             }
         }
-        Symmetry::Vertical => {
-            let center_y = map.height / 2;
-            if y == center_y {
-                apply_paint(map, brush_size, x, y);
-            } else {
-                let dist_y = i32::abs(center_y - y);
-                apply_paint(map, brush_size, x, center_y + dist_y);
-                apply_paint(map, brush_size, x, center_y - dist_y);
+
+        let mut idx = match reached { Some(idx) => idx, None => continue };
+        while came_from[idx] != idx {
+            idx = came_from[idx];
+            if !is_walkable(map.tiles[idx]) {
+                map.tiles[idx] = TileType::Floor;
+                carved.push(idx);
             }
+            connected[idx] = true;
         }
-        Symmetry::Both => {
-            let center_x = map.width / 2;
-            let center_y = map.height / 2;
-            if x == center_x && y == center_y {
-                apply_paint(map, brush_size, x, y);
-            } else {
-                let dist_x = i32::abs(center_x - x);
-                apply_paint(map, brush_size, center_x + dist_x, y);
-                apply_paint(map, brush_size, center_x - dist_x, y);
-                let dist_y = i32::abs(center_y - y);
-                apply_paint(map, brush_size, x, center_y + dist_y);
-                apply_paint(map, brush_size, x, center_y - dist_y);
+        for idx in area { connected[idx] = true; }
+    }
+
+    carved
+}
+
+/// The tile nearest the centre of the largest walkable area, or the centre itself if
+/// nothing is walkable
+pub(crate) fn central_start(map: &Map) -> Position {
+    let centre = Position::new(map.width / 2, map.height / 2);
+    walkable_regions(map).into_iter()
+        .max_by_key(|region| region.len())
+        .and_then(|region| region.into_iter()
+            .map(|idx| Position::new(idx as i32 % map.width, idx as i32 / map.width))
+            .min_by_key(|p| (p.x - centre.x).pow(2) + (p.y - centre.y).pow(2)))
+        .unwrap_or(centre)
+}
+
+/// Paints floor with the given brush and symmetry, returning the indices of the tiles which
+/// weren't floor before. Generators use this to keep a running floor count. Every tile under
+/// the brush is carved along with all of its images, and nothing is carved on the outer edge,
+/// so the result is as symmetric as the mode allows.
+pub(crate) fn paint(map: &mut Map, mode: Symmetry, brush_size: i32, x: i32, y:i32) -> Vec<usize> {
+    paint_with(map, mode, Brush::from_size(brush_size), x, y)
+}
+
+/// `paint` with any brush rather than a square of the given size
+pub(crate) fn paint_with(map: &mut Map, mode: Symmetry, brush: Brush, x: i32, y:i32) -> Vec<usize> {
+    let mut carved = Vec::new();
+
+    for tile in brush.footprint(map.grid, x, y) {
+        for image in mode.images(map.width, map.height, tile.x, tile.y) {
+            if image.x >= 1 && image.x <= map.width - 2 && image.y >= 1 && image.y <= map.height - 2 {
+                carve(map, image.x, image.y, &mut carved);
             }
         }
     }
+
+    carved
 }
 
-fn apply_paint(map: &mut Map, brush_size: i32, x: i32, y: i32) {
-    match brush_size {
-        1 => map.set_tile(x, y, TileType::Floor),
-        _ => {
-            let half_brush_size = brush_size / 2;
-            for brush_y in y - half_brush_size .. y + half_brush_size {
-                for brush_x in x - half_brush_size .. x + half_brush_size {
-                    if brush_x > 1 && brush_x < map.width - 1 && brush_y > 1 && brush_y < map.height - 1 {
-                        map.set_tile(brush_x, brush_y, TileType::Floor);
-                    }
-                }
-            }
+/// True with the given probability
+pub(crate) fn roll(chance: f32, rng: &mut RandomNumberGenerator) -> bool {
+    chance > 0.0 && rng.range(0, 10_000) < (chance * 10_000.0) as i32
+}
+
+/// Carves a line from `start` to each of its images, so every symmetric copy of the area
+/// around the start stays reachable from it. Call before removing unreachable areas.
+pub(crate) fn connect_images(map: &mut Map, mode: Symmetry, start: Position) {
+    for image in mode.images(map.width, map.height, start.x, start.y).into_iter().skip(1) {
+        let path = line_points(map.grid, start.x, start.y, image.x, image.y);
+        for p in path {
+            paint(map, mode, 1, p.x, p.y);
         }
     }
 }
+
+fn carve(map: &mut Map, x: i32, y: i32, carved: &mut Vec<usize>) {
+    let idx = map.xy_idx(x, y);
+    if map.tiles[idx] != TileType::Floor {
+        map.tiles[idx] = TileType::Floor;
+        carved.push(idx);
+    }
+}
@@ -0,0 +1,99 @@
+//! Invariant checks for generated maps
+//!
+//! `Map::validate` looks for the things a game is likely to trip over: a start it can't stand
+//! on, exits it can't reach, holes in the border, rooms hanging off the map, floor nobody can
+//! get to and leftover `Void` tiles. An empty report means the map is safe to use.
+
+use super::stats::walkable_regions;
+use super::utils::{Map, Position, Room, TileType};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapProblem {
+    /// The start is off the map or on a tile which can't be walked on
+    StartNotWalkable(Position),
+    /// There are no exit tiles at all
+    NoExit,
+    /// An exit which can't be walked to from the start
+    UnreachableExit(Position),
+    /// Tiles on the edge of the map which aren't wall
+    OpenBorder(Vec<Position>),
+    /// A room whose rectangle goes past the edge of the map
+    RoomOutOfBounds(Room),
+    /// A walkable area which isn't connected to the start, given as its tile indices
+    IsolatedPocket(Vec<usize>),
+    /// Tiles which were never filled in
+    StrayVoid(Vec<Position>)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    pub problems: Vec<MapProblem>
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl Map {
+    /// Checks the map against the invariants every generator is meant to uphold
+    pub fn validate(&self) -> ValidationReport {
+        let mut problems = Vec::new();
+        let position_of = |idx: usize| Position::new(idx as i32 % self.width, idx as i32 / self.width);
+
+        let start = self.start_position;
+        let start_idx = self.in_bounds(start.x, start.y).then(|| self.xy_idx(start.x, start.y));
+        let start_walkable = start_idx.is_some_and(|idx| matches!(self.tiles[idx], TileType::Floor | TileType::Exit));
+        if !start_walkable {
+            problems.push(MapProblem::StartNotWalkable(start));
+        }
+
+        // Everything reachable from the start is one region; every other region is cut off
+        let mut reachable = vec![false; self.tiles.len()];
+        for region in walkable_regions(self) {
+            if start_walkable && start_idx.is_some_and(|idx| region.contains(&idx)) {
+                for idx in region {
+                    reachable[idx] = true;
+                }
+            } else if region.iter().any(|idx| self.tiles[*idx] == TileType::Floor) {
+                problems.push(MapProblem::IsolatedPocket(region));
+            }
+        }
+
+        let exits: Vec<usize> = (0..self.tiles.len()).filter(|idx| self.tiles[*idx] == TileType::Exit).collect();
+        if exits.is_empty() {
+            problems.push(MapProblem::NoExit);
+        }
+        for idx in exits {
+            if !reachable[idx] {
+                problems.push(MapProblem::UnreachableExit(position_of(idx)));
+            }
+        }
+
+        let open_border: Vec<Position> = (0..self.tiles.len())
+            .map(position_of)
+            .filter(|p| p.x == 0 || p.y == 0 || p.x == self.width - 1 || p.y == self.height - 1)
+            .filter(|p| self.get_tile(p.x, p.y) != TileType::Wall)
+            .collect();
+        if !open_border.is_empty() {
+            problems.push(MapProblem::OpenBorder(open_border));
+        }
+
+        for room in self.rooms.iter() {
+            if room.x1 < 0 || room.y1 < 0 || room.x2 >= self.width || room.y2 >= self.height {
+                problems.push(MapProblem::RoomOutOfBounds(*room));
+            }
+        }
+
+        let void: Vec<Position> = (0..self.tiles.len())
+            .filter(|idx| self.tiles[*idx] == TileType::Void)
+            .map(position_of)
+            .collect();
+        if !void.is_empty() {
+            problems.push(MapProblem::StrayVoid(void));
+        }
+
+        ValidationReport { problems }
+    }
+}
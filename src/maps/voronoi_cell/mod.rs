@@ -1,132 +1,337 @@
-use bracket_pathfinding::prelude::*;
+use crate::maps::{draw::Brush, hex::hex_distance, utils::*, Architect};
 
-use crate::maps::{utils::*, Architect};
-
-#[derive(PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub enum DistanceAlgorithm {
     Pythagoras,
     Manhattan,
     Chebyshev
 }
 
+/// Where the cell seeds go
+#[derive(Debug, Clone, PartialEq)]
+pub enum SeedPlacement {
+    /// Anywhere, independently of each other
+    Uniform,
+    /// Randomly, but never closer than `min_distance` to another seed. Seeds which can't be
+    /// fitted in after a fair number of attempts are left out, so a large distance can give
+    /// fewer seeds than asked for.
+    PoissonDisc { min_distance: f32 },
+    /// One seed in each square of a grid sized to fit the seed count, moved off the middle of
+    /// its square by up to `jitter` of the square's width (0 keeps a perfect grid, 1 lets it
+    /// go anywhere in the square)
+    JitteredGrid { jitter: f32 },
+    /// Exactly these seeds; `n_seeds` is ignored
+    Custom(Vec<Position>)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoronoiSettings {
+    pub n_seeds: usize,
+    pub distance_algorithm: DistanceAlgorithm,
+    pub placement: SeedPlacement,
+    /// Walls between cells: 1 is a thin wall and each step past that adds a tile to either
+    /// side, while 0 leaves the cells open to each other
+    pub wall_thickness: i32
+}
+
 pub struct VoronoiCellMap {
     pub map: Map,
     pub width: i32,
     pub height: i32,
-    n_seeds: usize,
-    distance_algorithm: DistanceAlgorithm
+    pub settings: VoronoiSettings,
+    /// The seed positions used by the last build
+    pub seeds: Vec<Position>,
+    /// Region layer from the last build: the index into `seeds` that each tile belongs to
+    pub membership: Vec<usize>
+}
+
+/// Distance between two positions in whole units. Pythagoras is left squared, which keeps it
+/// exact and orders the same way.
+fn seed_distance(grid: GridType, algorithm: DistanceAlgorithm, dx: i64, dy: i64, a: Position, b: Position) -> i64 {
+    match algorithm {
+        _ if grid == GridType::Hex => hex_distance(a, b) as i64,
+        DistanceAlgorithm::Pythagoras => dx * dx + dy * dy,
+        DistanceAlgorithm::Manhattan => dx.abs() + dy.abs(),
+        DistanceAlgorithm::Chebyshev => i64::max(dx.abs(), dy.abs())
+    }
+}
+
+/// The smallest distance possible to a tile at least `gap` tiles away along the x or y axis.
+fn min_distance_for_gap(grid: GridType, algorithm: DistanceAlgorithm, gap: i64) -> i64 {
+    match algorithm {
+        // Moving along a hex row, every two rows covered also shifts half a tile sideways
+        _ if grid == GridType::Hex => (2 * gap - 1) / 3,
+        DistanceAlgorithm::Pythagoras => gap * gap,
+        DistanceAlgorithm::Manhattan | DistanceAlgorithm::Chebyshev => gap
+    }
+}
+
+/// Works out which seed each tile of the map is closest to, returning the index of that seed
+/// for every tile. Ties go to the seed listed first.
+///
+/// Rather than measuring every tile against every seed, the seeds are dropped into square
+/// bins roughly one seed apart. Each tile searches outwards a ring of bins at a time, and
+/// stops as soon as no unsearched bin could hold anything closer than its best match.
+pub fn voronoi_membership(map: &Map, seeds: &[Position], algorithm: DistanceAlgorithm) -> Vec<usize> {
+    let mut membership = vec![0; map.tiles.len()];
+    if seeds.is_empty() { return membership; }
+
+    let area = (map.width * map.height) as f32;
+    let bin_size = i32::max(1, (area / seeds.len() as f32).sqrt() as i32);
+    let bins_wide = (map.width + bin_size - 1) / bin_size;
+    let bins_high = (map.height + bin_size - 1) / bin_size;
+
+    let mut bins: Vec<Vec<usize>> = vec![Vec::new(); (bins_wide * bins_high) as usize];
+    for (seed, pos) in seeds.iter().enumerate() {
+        let bx = (pos.x / bin_size).clamp(0, bins_wide - 1);
+        let by = (pos.y / bin_size).clamp(0, bins_high - 1);
+        bins[(by * bins_wide + bx) as usize].push(seed);
+    }
+
+    let max_ring = i32::max(bins_wide, bins_high);
+    for (idx, member) in membership.iter_mut().enumerate() {
+        let tile = Position::new(idx as i32 % map.width, idx as i32 / map.width);
+        let (bx, by) = (tile.x / bin_size, tile.y / bin_size);
+        let mut best = (i64::MAX, usize::MAX);
+
+        for ring in 0..=max_ring {
+            for y in by - ring ..= by + ring {
+                if y < 0 || y >= bins_high { continue; }
+                for x in bx - ring ..= bx + ring {
+                    if x < 0 || x >= bins_wide { continue; }
+                    // Only the outline of the ring; the inside was searched already
+                    if ring > 0 && y != by - ring && y != by + ring && x != bx - ring && x != bx + ring { continue; }
+
+                    for seed in bins[(y * bins_wide + x) as usize].iter() {
+                        let pos = seeds[*seed];
+                        let dx = (pos.x - tile.x) as i64;
+                        let dy = (pos.y - tile.y) as i64;
+                        let distance = seed_distance(map.grid, algorithm, dx, dy, tile, pos);
+                        if distance < best.0 || (distance == best.0 && *seed < best.1) {
+                            best = (distance, *seed);
+                        }
+                    }
+                }
+            }
+
+            // Anything in the next ring is at least `ring * bin_size + 1` tiles away on some axis
+            let gap = (ring * bin_size + 1) as i64;
+            if best.1 != usize::MAX && min_distance_for_gap(map.grid, algorithm, gap) > best.0 {
+                break;
+            }
+        }
+
+        *member = best.1;
+    }
+
+    membership
+}
+
+/// Splits the walkable tiles of any map into up to `n_regions` areas around seeds picked from
+/// those tiles, e.g. to spread encounters over a cave with no rooms. Region `i` is the list of
+/// tile indices in `regions[i]`, and every region holds at least its own seed. Distances are
+/// straight line, ignoring walls, so a region can take in tiles on both sides of a thin wall.
+pub fn voronoi_regions(map: &Map, n_regions: usize, algorithm: DistanceAlgorithm, rng: &mut RandomNumberGenerator) -> Vec<Vec<usize>> {
+    let mut walkable: Vec<usize> = (0..map.tiles.len())
+        .filter(|idx| matches!(map.tiles[*idx], TileType::Floor | TileType::Exit))
+        .collect();
+
+    // A partial shuffle picks the seeds without repeats
+    let n_regions = usize::min(n_regions, walkable.len());
+    for i in 0..n_regions {
+        let j = rng.range(i as i32, walkable.len() as i32) as usize;
+        walkable.swap(i, j);
+    }
+    let seeds: Vec<Position> = walkable[..n_regions].iter()
+        .map(|idx| Position::new(*idx as i32 % map.width, *idx as i32 / map.width))
+        .collect();
+    walkable.sort_unstable();
+
+    let membership = voronoi_membership(map, &seeds, algorithm);
+    let mut regions = vec![Vec::new(); n_regions];
+    if n_regions > 0 {
+        for idx in walkable {
+            regions[membership[idx]].push(idx);
+        }
+    }
+    regions
 }
 
 impl VoronoiCellMap {
-    pub fn pythagoras(width: i32, height: i32) -> Self {
+    pub fn with_settings(width: i32, height: i32, settings: VoronoiSettings) -> Self {
         Self {
             map: Map::new(width, height),
             width,
             height,
-            n_seeds: 64,
-            distance_algorithm: DistanceAlgorithm::Pythagoras
+            settings,
+            seeds: Vec::new(),
+            membership: Vec::new()
         }
     }
 
+    /// 64 randomly placed cells, measured with the given distance
+    pub fn with_algorithm(width: i32, height: i32, distance_algorithm: DistanceAlgorithm) -> Self {
+        Self::with_settings(width, height, VoronoiSettings {
+            n_seeds: 64,
+            distance_algorithm,
+            placement: SeedPlacement::Uniform,
+            wall_thickness: 1
+        })
+    }
+
+    pub fn pythagoras(width: i32, height: i32) -> Self {
+        Self::with_algorithm(width, height, DistanceAlgorithm::Pythagoras)
+    }
+
     pub fn manhattan(width: i32, height: i32) -> Self {
-        Self {
-            map: Map::new(width, height),
-            width,
-            height,
+        Self::with_algorithm(width, height, DistanceAlgorithm::Manhattan)
+    }
+
+    pub fn chebyshev(width: i32, height: i32) -> Self {
+        Self::with_algorithm(width, height, DistanceAlgorithm::Chebyshev)
+    }
+
+    /// Evenly sized cells from a jittered grid of seeds
+    pub fn even_cells(width: i32, height: i32) -> Self {
+        Self::with_settings(width, height, VoronoiSettings {
             n_seeds: 64,
-            distance_algorithm: DistanceAlgorithm::Manhattan
-        }
+            distance_algorithm: DistanceAlgorithm::Pythagoras,
+            placement: SeedPlacement::JitteredGrid { jitter: 0.6 },
+            wall_thickness: 1
+        })
     }
-}
 
-impl Architect for VoronoiCellMap {
-    fn build(&mut self) {
-        let mut rng = RandomNumberGenerator::new();
-
-        // Make a Voronoi diagram. We'll do this the hard way to learn about the technique!
-        let mut voronoi_seeds : Vec<(usize, Point)> = Vec::new();
-
-        while voronoi_seeds.len() < self.n_seeds {
-            let vx = rng.roll_dice(1, self.width-1);
-            let vy = rng.roll_dice(1, self.height-1);
-            let vidx = self.map.xy_idx(vx, vy);
-            let candidate = (vidx, Point::new(vx, vy));
-            if !voronoi_seeds.contains(&candidate) {
-                voronoi_seeds.push(candidate);
+    /// Builds on a hex grid instead of a square one. Hex cells always use hex step distance,
+    /// whichever distance algorithm was selected.
+    pub fn on_hex_grid(mut self) -> Self {
+        self.map = Map::new_hex(self.width, self.height);
+        self
+    }
+
+    /// Places the seeds inside the border, never two on the same tile
+    fn place_seeds(&mut self, rng: &mut RandomNumberGenerator) {
+        let available = ((self.width - 2) * (self.height - 2)).max(0) as usize;
+        let n_seeds = usize::min(self.settings.n_seeds, available);
+        let mut taken = vec![false; self.map.tiles.len()];
+        self.seeds.clear();
+
+        let mut try_seed = |seeds: &mut Vec<Position>, x: i32, y: i32| {
+            let (x, y) = (x.clamp(1, self.width - 2), y.clamp(1, self.height - 2));
+            let idx = (y * self.width + x) as usize;
+            if !taken[idx] {
+                taken[idx] = true;
+                seeds.push(Position::new(x, y));
             }
-        }
+        };
 
-        let mut voronoi_distance = vec![(0, 0.0f32) ; self.n_seeds];
-        let mut voronoi_membership : Vec<i32> = vec![0 ; self.width as usize * self.height as usize];
-        for (i, vid) in voronoi_membership.iter_mut().enumerate() {
-            let x = i as i32 % self.width;
-            let y = i as i32 / self.width;
-
-            for (seed, pos) in voronoi_seeds.iter().enumerate() {
-                let distance;
-                match self.distance_algorithm {
-                    DistanceAlgorithm::Pythagoras => {
-                        distance = DistanceAlg::PythagorasSquared.distance2d(
-                            Point::new(x, y),
-                            pos.1
-                        );
-                    }
-                    DistanceAlgorithm::Manhattan => {
-                        distance = DistanceAlg::Manhattan.distance2d(
-                            Point::new(x, y),
-                            pos.1
-                        );
-                    }
-                    DistanceAlgorithm::Chebyshev => {
-                        distance = DistanceAlg::Chebyshev.distance2d(
-                            Point::new(x, y),
-                            pos.1
-                        );
+        match &self.settings.placement {
+            SeedPlacement::Uniform => {
+                while self.seeds.len() < n_seeds {
+                    let (vx, vy) = (rng.roll_dice(1, self.width - 2), rng.roll_dice(1, self.height - 2));
+                    try_seed(&mut self.seeds, vx, vy);
+                }
+            },
+            SeedPlacement::PoissonDisc { min_distance } => {
+                let min_squared = min_distance * min_distance;
+                let mut attempts = 0;
+                while self.seeds.len() < n_seeds && attempts < n_seeds * 30 {
+                    attempts += 1;
+                    let (vx, vy) = (rng.roll_dice(1, self.width - 2), rng.roll_dice(1, self.height - 2));
+                    let clear = self.seeds.iter().all(|s| {
+                        let (dx, dy) = ((s.x - vx) as f32, (s.y - vy) as f32);
+                        dx * dx + dy * dy >= min_squared
+                    });
+                    if clear { try_seed(&mut self.seeds, vx, vy); }
+                }
+            },
+            SeedPlacement::JitteredGrid { jitter } => {
+                // Square grid cells, as many as fit the seed count over the inner area
+                let (inner_w, inner_h) = ((self.width - 2) as f32, (self.height - 2) as f32);
+                let cell = f32::max(1.0, (inner_w * inner_h / n_seeds.max(1) as f32).sqrt());
+                let (columns, rows) = ((inner_w / cell).round().max(1.0) as i32, (inner_h / cell).round().max(1.0) as i32);
+                let (cell_w, cell_h) = (inner_w / columns as f32, inner_h / rows as f32);
+                let jitter = jitter.clamp(0.0, 1.0);
+
+                for row in 0..rows {
+                    for column in 0..columns {
+                        let offset_x = (rng.range(0, 1001) as f32 / 1000.0 - 0.5) * jitter;
+                        let offset_y = (rng.range(0, 1001) as f32 / 1000.0 - 0.5) * jitter;
+                        let x = 1.0 + (column as f32 + 0.5 + offset_x) * cell_w;
+                        let y = 1.0 + (row as f32 + 0.5 + offset_y) * cell_h;
+                        try_seed(&mut self.seeds, x as i32, y as i32);
                     }
                 }
-                voronoi_distance[seed] = (seed, distance);
+            },
+            SeedPlacement::Custom(points) => {
+                for p in points.iter() {
+                    if self.map.in_bounds(p.x, p.y) { try_seed(&mut self.seeds, p.x, p.y); }
+                }
             }
-
-            voronoi_distance.sort_by(|a,b| a.1.partial_cmp(&b.1).unwrap());
-
-            *vid = voronoi_distance[0].0 as i32;
         }
+    }
+}
 
+impl Architect for VoronoiCellMap {
+    fn build_with_rng(&mut self, rng: &mut RandomNumberGenerator) {
+        self.map.clear();
+        self.place_seeds(rng);
 
+        self.membership = voronoi_membership(&self.map, &self.seeds, self.settings.distance_algorithm);
+        let voronoi_membership = &self.membership;
+
+        // Tiles on a cell border, which thicker walls then spread out from
+        let mut border = vec![false; self.map.tiles.len()];
         for y in 1..self.height - 1 {
             for x in 1..self.width - 1 {
-                let mut neighbors = 0;
                 let my_idx = self.map.xy_idx(x, y);
                 let my_seed = voronoi_membership[my_idx];
-                if voronoi_membership[self.map.xy_idx(x-1, y)] != my_seed { neighbors += 1; }
-                if voronoi_membership[self.map.xy_idx(x+1, y)] != my_seed { neighbors += 1; }
-                if voronoi_membership[self.map.xy_idx(x, y-1)] != my_seed { neighbors += 1; }
-                if voronoi_membership[self.map.xy_idx(x, y+1)] != my_seed { neighbors += 1; }
+                let neighbors = self.map.adjacent(x, y)
+                    .iter()
+                    .filter(|p| voronoi_membership[self.map.xy_idx(p.x, p.y)] != my_seed)
+                    .count();
 
-                if neighbors < 2 {
-                    self.map.set_tile_at_idx(my_idx, TileType::Floor);
-                }
+                // Every hex on a cell border touches at least two hexes of another cell, so
+                // allow one more before walling it off or the cells never connect
+                let threshold = match self.map.grid {
+                    GridType::Square => 2,
+                    GridType::Hex => 3
+                };
+
+                border[my_idx] = neighbors >= threshold && self.settings.wall_thickness > 0;
             }
         }
 
-        // Find a starting point; start at the middle and walk left until we find an open tile
-        let mut start_position = Position::new(self.width / 2, self.height / 2);
-        let mut start_idx = self.map.xy_idx(start_position.x, start_position.y);
-        while self.map.tiles[start_idx] != TileType::Floor {
-            start_position.x -= 1;
-            start_idx = self.map.xy_idx(start_position.x, start_position.y);
+        let spread = Brush::square(self.settings.wall_thickness - 1);
+        for y in 1..self.height - 1 {
+            for x in 1..self.width - 1 {
+                let walled = spread.footprint(self.map.grid, x, y).iter()
+                    .any(|p| self.map.in_bounds(p.x, p.y) && border[self.map.xy_idx(p.x, p.y)]);
+                if !walled {
+                    self.map.set_tile(x, y, TileType::Floor);
+                }
+            }
         }
+        self.map.take_snapshot();
 
+        // Start in the biggest open area, carving a tile if the walls left no floor at all
+        let start_position = central_start(&self.map);
+        self.map.set_tile(start_position.x, start_position.y, TileType::Floor);
+        let start_idx = self.map.xy_idx(start_position.x, start_position.y);
         self.map.start_position = start_position;
 
         let exit_tile = remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx);
         self.map.set_tile_at_idx(exit_tile, TileType::Exit);
+        self.map.take_snapshot();
     }
 
     fn get_map(&self) -> &Map {
         &self.map
     }
 
+    fn get_map_mut(&mut self) -> &mut Map {
+        &mut self.map
+    }
+
     fn start_position(&self) -> (i32, i32) {
         (self.map.start_position.x, self.map.start_position.y)
     }
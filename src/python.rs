@@ -0,0 +1,572 @@
+//! Python bindings
+//!
+//! Built as the `cartographer` extension module with maturin (see `pyproject.toml`). Maps
+//! hand their tiles back through the buffer protocol, so `numpy.asarray(map.tiles)` gives a
+//! `(height, width)` array of `uint8` tile codes without copying them again. The smoke tests
+//! in `tests/test_python.py` run against a `maturin develop` build.
+
+use std::ffi::{c_int, c_void};
+use std::ptr;
+
+use pyo3::exceptions::{PyBufferError, PyValueError};
+use pyo3::ffi::Py_ssize_t;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::maps::batch::generate_batch as build_batch;
+use crate::maps::dla::{DlaAlgorithm, DlaMap, DlaSettings};
+use crate::maps::drunkard_walk::{DrunkSpawnMode, DrunkardSettings, DrunkardWalkMap};
+use crate::maps::stats::MapStats;
+use crate::maps::utils::{GridType, Map, RandomNumberGenerator, Symmetry, TileType};
+use crate::maps::{Architect, MapSelector};
+
+fn tile_code(tile: TileType) -> u8 {
+    match tile {
+        TileType::Wall => 0,
+        TileType::Floor => 1,
+        TileType::Exit => 2,
+        TileType::Void => 3
+    }
+}
+
+fn parse_symmetry(name: &str) -> PyResult<Symmetry> {
+    let folds = name.strip_prefix("radial:").map(|n| n.parse::<u32>());
+    match (name, folds) {
+        ("none", _) => Ok(Symmetry::None),
+        ("horizontal", _) => Ok(Symmetry::Horizontal),
+        ("vertical", _) => Ok(Symmetry::Vertical),
+        ("both", _) => Ok(Symmetry::Both),
+        ("rotational2", _) => Ok(Symmetry::Rotational2),
+        ("rotational4", _) => Ok(Symmetry::Rotational4),
+        (_, Some(Ok(folds))) if folds > 0 => Ok(Symmetry::Radial(folds)),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown symmetry '{}', expected none, horizontal, vertical, both, rotational2, rotational4 or radial:N", name
+        )))
+    }
+}
+
+fn symmetry_name(symmetry: Symmetry) -> String {
+    match symmetry {
+        Symmetry::None => "none".to_string(),
+        Symmetry::Horizontal => "horizontal".to_string(),
+        Symmetry::Vertical => "vertical".to_string(),
+        Symmetry::Both => "both".to_string(),
+        Symmetry::Rotational2 => "rotational2".to_string(),
+        Symmetry::Rotational4 => "rotational4".to_string(),
+        Symmetry::Radial(folds) => format!("radial:{}", folds)
+    }
+}
+
+/// Tile codes laid out row by row, exposed as a read only 2D buffer of bytes
+#[pyclass(frozen, module = "cartographer")]
+struct Tiles {
+    data: Vec<u8>,
+    shape: [Py_ssize_t; 2],
+    strides: [Py_ssize_t; 2]
+}
+
+#[pymethods]
+impl Tiles {
+    unsafe fn __getbuffer__(slf: Bound<'_, Self>, view: *mut pyo3::ffi::Py_buffer, flags: c_int) -> PyResult<()> {
+        use pyo3::ffi::{PyBUF_FORMAT, PyBUF_ND, PyBUF_STRIDES, PyBUF_WRITABLE};
+
+        if view.is_null() {
+            return Err(PyBufferError::new_err("no buffer view to fill in"));
+        }
+        if flags & PyBUF_WRITABLE == PyBUF_WRITABLE {
+            return Err(PyBufferError::new_err("tiles are read only"));
+        }
+
+        let tiles = slf.get();
+        let view = &mut *view;
+        view.buf = tiles.data.as_ptr() as *mut c_void;
+        view.len = tiles.data.len() as Py_ssize_t;
+        view.readonly = 1;
+        view.itemsize = 1;
+        view.format = if flags & PyBUF_FORMAT == PyBUF_FORMAT { c"B".as_ptr() as *mut _ } else { ptr::null_mut() };
+
+        // Consumers which don't ask for a shape get the same bytes as one flat array
+        if flags & PyBUF_ND == PyBUF_ND {
+            view.ndim = 2;
+            view.shape = tiles.shape.as_ptr() as *mut _;
+        } else {
+            view.ndim = 1;
+            view.shape = ptr::null_mut();
+        }
+        view.strides = if flags & PyBUF_STRIDES == PyBUF_STRIDES { tiles.strides.as_ptr() as *mut _ } else { ptr::null_mut() };
+        view.suboffsets = ptr::null_mut();
+        view.internal = ptr::null_mut();
+        // The view keeps a reference to us so the data outlives it
+        view.obj = slf.into_any().into_ptr();
+
+        Ok(())
+    }
+
+    unsafe fn __releasebuffer__(&self, _view: *mut pyo3::ffi::Py_buffer) {}
+
+    fn __len__(&self) -> usize {
+        self.data.len()
+    }
+}
+
+/// A generated map
+#[pyclass(frozen, name = "Map", module = "cartographer")]
+struct PyMap {
+    map: Map,
+    #[pyo3(get)]
+    seed: u64,
+    #[pyo3(get)]
+    tiles: Py<Tiles>
+}
+
+impl PyMap {
+    fn new(py: Python<'_>, map: Map, seed: u64) -> PyResult<Self> {
+        let tiles = Tiles {
+            data: map.tiles.iter().map(|t| tile_code(*t)).collect(),
+            shape: [map.height as Py_ssize_t, map.width as Py_ssize_t],
+            strides: [map.width as Py_ssize_t, 1]
+        };
+        Ok(Self { tiles: Py::new(py, tiles)?, map, seed })
+    }
+}
+
+#[pymethods]
+impl PyMap {
+    #[getter]
+    fn width(&self) -> i32 {
+        self.map.width
+    }
+
+    #[getter]
+    fn height(&self) -> i32 {
+        self.map.height
+    }
+
+    /// "square" or "hex"
+    #[getter]
+    fn grid(&self) -> &'static str {
+        match self.map.grid {
+            GridType::Square => "square",
+            GridType::Hex => "hex"
+        }
+    }
+
+    /// Start position as `(x, y)`
+    #[getter]
+    fn start(&self) -> (i32, i32) {
+        (self.map.start_position.x, self.map.start_position.y)
+    }
+
+    /// Position of the first exit tile as `(x, y)`, if there is one
+    #[getter]
+    fn exit(&self) -> Option<(i32, i32)> {
+        self.map.tiles.iter()
+            .position(|t| *t == TileType::Exit)
+            .map(|idx| (idx as i32 % self.map.width, idx as i32 / self.map.width))
+    }
+
+    /// Rooms as `(x1, y1, x2, y2)`; only the room based generators produce any
+    #[getter]
+    fn rooms(&self) -> Vec<(i32, i32, i32, i32)> {
+        self.map.rooms.iter().map(|r| (r.x1, r.y1, r.x2, r.y2)).collect()
+    }
+
+    /// Floor ratio, region count and start to exit distance as a dict
+    fn stats<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let stats = MapStats::from_map(&self.map);
+        let dict = PyDict::new(py);
+        dict.set_item("floor_ratio", stats.floor_ratio)?;
+        dict.set_item("region_count", stats.region_count)?;
+        dict.set_item("start_exit_distance", stats.start_exit_distance)?;
+        Ok(dict)
+    }
+
+    fn __str__(&self) -> String {
+        let mut out = String::with_capacity(self.map.tiles.len() + self.map.height as usize);
+        for row in self.map.tiles.chunks(self.map.width as usize) {
+            out.extend(row.iter().map(|t| match t {
+                TileType::Wall => '#',
+                TileType::Floor => '.',
+                TileType::Exit => 'E',
+                TileType::Void => ' '
+            }));
+            out.push('\n');
+        }
+        out
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<Map {}x{} {} seed={}>", self.map.width, self.map.height, self.grid(), self.seed)
+    }
+}
+
+/// Settings for a drunkard's walk map. Defaults to the `open_area` preset.
+#[pyclass(name = "DrunkardSettings", module = "cartographer")]
+#[derive(Clone)]
+struct PyDrunkardSettings {
+    settings: DrunkardSettings
+}
+
+#[pymethods]
+impl PyDrunkardSettings {
+    #[new]
+    #[pyo3(signature = (spawn_mode = "starting_point", lifetime = 400, floor_percent = 0.5, brush_size = 1, symmetry = "none"))]
+    fn new(spawn_mode: &str, lifetime: i32, floor_percent: f32, brush_size: i32, symmetry: &str) -> PyResult<Self> {
+        let mut settings = Self { settings: DrunkardWalkMap::open_area(0, 0).settings };
+        settings.set_spawn_mode(spawn_mode)?;
+        settings.set_symmetry(symmetry)?;
+        settings.settings.lifetime = lifetime;
+        settings.settings.floor_percent = floor_percent;
+        settings.settings.brush_size = brush_size;
+        Ok(settings)
+    }
+
+    #[staticmethod]
+    fn open_area() -> Self {
+        Self { settings: DrunkardWalkMap::open_area(0, 0).settings }
+    }
+
+    #[staticmethod]
+    fn open_halls() -> Self {
+        Self { settings: DrunkardWalkMap::open_halls(0, 0).settings }
+    }
+
+    #[staticmethod]
+    fn winding_passages() -> Self {
+        Self { settings: DrunkardWalkMap::winding_passages(0, 0).settings }
+    }
+
+    #[staticmethod]
+    fn fat_passages() -> Self {
+        Self { settings: DrunkardWalkMap::fat_passages(0, 0).settings }
+    }
+
+    #[staticmethod]
+    fn fearful_symmetry() -> Self {
+        Self { settings: DrunkardWalkMap::fearful_symmetry(0, 0).settings }
+    }
+
+    #[staticmethod]
+    fn mines() -> Self {
+        Self { settings: DrunkardWalkMap::mines(0, 0).settings }
+    }
+
+    #[staticmethod]
+    fn tunnels_and_chambers() -> Self {
+        Self { settings: DrunkardWalkMap::tunnels_and_chambers(0, 0).settings }
+    }
+
+    /// "starting_point" or "random"
+    #[getter]
+    fn spawn_mode(&self) -> &'static str {
+        match self.settings.spawn_mode {
+            DrunkSpawnMode::StartingPoint => "starting_point",
+            DrunkSpawnMode::Random => "random"
+        }
+    }
+
+    #[setter]
+    fn set_spawn_mode(&mut self, value: &str) -> PyResult<()> {
+        self.settings.spawn_mode = match value {
+            "starting_point" => DrunkSpawnMode::StartingPoint,
+            "random" => DrunkSpawnMode::Random,
+            _ => return Err(PyValueError::new_err(format!("unknown spawn mode '{}', expected starting_point or random", value)))
+        };
+        Ok(())
+    }
+
+    #[getter]
+    fn lifetime(&self) -> i32 {
+        self.settings.lifetime
+    }
+
+    #[setter]
+    fn set_lifetime(&mut self, value: i32) {
+        self.settings.lifetime = value;
+    }
+
+    #[getter]
+    fn floor_percent(&self) -> f32 {
+        self.settings.floor_percent
+    }
+
+    #[setter]
+    fn set_floor_percent(&mut self, value: f32) {
+        self.settings.floor_percent = value;
+    }
+
+    #[getter]
+    fn brush_size(&self) -> i32 {
+        self.settings.brush_size
+    }
+
+    #[setter]
+    fn set_brush_size(&mut self, value: i32) {
+        self.settings.brush_size = value;
+    }
+
+    #[getter]
+    fn max_diggers(&self) -> usize {
+        self.settings.max_diggers
+    }
+
+    #[setter]
+    fn set_max_diggers(&mut self, value: usize) {
+        self.settings.max_diggers = value;
+    }
+
+    /// "none", "horizontal", "vertical", "both", "rotational2", "rotational4" or "radial:N"
+    #[getter]
+    fn symmetry(&self) -> String {
+        symmetry_name(self.settings.symmetry)
+    }
+
+    #[setter]
+    fn set_symmetry(&mut self, value: &str) -> PyResult<()> {
+        self.settings.symmetry = parse_symmetry(value)?;
+        Ok(())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "DrunkardSettings(spawn_mode='{}', lifetime={}, floor_percent={}, brush_size={}, symmetry='{}')",
+            self.spawn_mode(), self.settings.lifetime, self.settings.floor_percent, self.settings.brush_size, self.symmetry()
+        )
+    }
+}
+
+/// Settings for a diffusion-limited aggregation map. Defaults to the `walk_inwards` preset.
+#[pyclass(name = "DlaSettings", module = "cartographer")]
+#[derive(Clone)]
+struct PyDlaSettings {
+    settings: DlaSettings
+}
+
+#[pymethods]
+impl PyDlaSettings {
+    #[new]
+    #[pyo3(signature = (algorithm = "walk_inwards", brush_size = 1, symmetry = "none", floor_percent = 0.45))]
+    fn new(algorithm: &str, brush_size: i32, symmetry: &str, floor_percent: f32) -> PyResult<Self> {
+        let mut settings = Self { settings: DlaMap::walk_inwards(0, 0).settings };
+        settings.set_algorithm(algorithm)?;
+        settings.set_symmetry(symmetry)?;
+        settings.settings.brush_size = brush_size;
+        settings.settings.floor_percent = floor_percent;
+        Ok(settings)
+    }
+
+    #[staticmethod]
+    fn walk_inwards() -> Self {
+        Self { settings: DlaMap::walk_inwards(0, 0).settings }
+    }
+
+    #[staticmethod]
+    fn walk_outwards() -> Self {
+        Self { settings: DlaMap::walk_outwards(0, 0).settings }
+    }
+
+    #[staticmethod]
+    fn central_attractor() -> Self {
+        Self { settings: DlaMap::central_attractor(0, 0).settings }
+    }
+
+    #[staticmethod]
+    fn insectoid() -> Self {
+        Self { settings: DlaMap::insectoid(0, 0).settings }
+    }
+
+    #[staticmethod]
+    fn coral() -> Self {
+        Self { settings: DlaMap::coral(0, 0).settings }
+    }
+
+    /// "walk_inwards", "walk_outwards" or "central_attractor"
+    #[getter]
+    fn algorithm(&self) -> &'static str {
+        match self.settings.algorithm {
+            DlaAlgorithm::WalkInwards => "walk_inwards",
+            DlaAlgorithm::WalkOutwards => "walk_outwards",
+            DlaAlgorithm::CentralAttractor => "central_attractor"
+        }
+    }
+
+    #[setter]
+    fn set_algorithm(&mut self, value: &str) -> PyResult<()> {
+        self.settings.algorithm = match value {
+            "walk_inwards" => DlaAlgorithm::WalkInwards,
+            "walk_outwards" => DlaAlgorithm::WalkOutwards,
+            "central_attractor" => DlaAlgorithm::CentralAttractor,
+            _ => return Err(PyValueError::new_err(format!(
+                "unknown algorithm '{}', expected walk_inwards, walk_outwards or central_attractor", value
+            )))
+        };
+        Ok(())
+    }
+
+    #[getter]
+    fn brush_size(&self) -> i32 {
+        self.settings.brush_size
+    }
+
+    #[setter]
+    fn set_brush_size(&mut self, value: i32) {
+        self.settings.brush_size = value;
+    }
+
+    /// "none", "horizontal", "vertical", "both", "rotational2", "rotational4" or "radial:N"
+    #[getter]
+    fn symmetry(&self) -> String {
+        symmetry_name(self.settings.symmetry)
+    }
+
+    #[setter]
+    fn set_symmetry(&mut self, value: &str) -> PyResult<()> {
+        self.settings.symmetry = parse_symmetry(value)?;
+        Ok(())
+    }
+
+    #[getter]
+    fn floor_percent(&self) -> f32 {
+        self.settings.floor_percent
+    }
+
+    #[setter]
+    fn set_floor_percent(&mut self, value: f32) {
+        self.settings.floor_percent = value;
+    }
+
+    #[getter]
+    fn stickiness(&self) -> f32 {
+        self.settings.stickiness
+    }
+
+    #[setter]
+    fn set_stickiness(&mut self, value: f32) {
+        self.settings.stickiness = value;
+    }
+
+    #[getter]
+    fn bias(&self) -> f32 {
+        self.settings.bias
+    }
+
+    #[setter]
+    fn set_bias(&mut self, value: f32) {
+        self.settings.bias = value;
+    }
+
+    #[getter]
+    fn max_walker_steps(&self) -> usize {
+        self.settings.max_walker_steps
+    }
+
+    #[setter]
+    fn set_max_walker_steps(&mut self, value: usize) {
+        self.settings.max_walker_steps = value;
+    }
+
+    #[getter]
+    fn max_walkers(&self) -> usize {
+        self.settings.max_walkers
+    }
+
+    #[setter]
+    fn set_max_walkers(&mut self, value: usize) {
+        self.settings.max_walkers = value;
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "DlaSettings(algorithm='{}', brush_size={}, symmetry='{}', floor_percent={})",
+            self.algorithm(), self.settings.brush_size, self.symmetry(), self.settings.floor_percent
+        )
+    }
+}
+
+/// What to build: the name of a `MapSelector` generator, or a settings object
+#[derive(FromPyObject)]
+enum Generator {
+    Name(String),
+    Drunkard(PyDrunkardSettings),
+    Dla(PyDlaSettings)
+}
+
+impl Generator {
+    /// Checks everything that could stop an architect being made, so `architect` can't fail
+    fn validate(&self, width: i32, height: i32) -> PyResult<()> {
+        // Settings objects need as much room as the presets they start from
+        let name = match self {
+            Generator::Name(name) => name.as_str(),
+            Generator::Drunkard(_) => "drunkard_walk_map_open_area",
+            Generator::Dla(_) => "dla_map_walk_inwards"
+        };
+        let Some(min_size) = MapSelector::min_size(name) else {
+            return Err(PyValueError::new_err(format!("unknown generator '{}', see cartographer.GENERATORS", name)));
+        };
+        if width < min_size || height < min_size {
+            return Err(PyValueError::new_err(format!("width and height must be at least {} for {}", min_size, name)));
+        }
+        Ok(())
+    }
+
+    fn architect(&self, width: i32, height: i32) -> Box<dyn Architect> {
+        match self {
+            Generator::Name(name) => MapSelector::by_name(name, width, height).unwrap(),
+            Generator::Drunkard(s) => Box::new(DrunkardWalkMap::with_settings(width, height, s.settings.clone())),
+            Generator::Dla(s) => Box::new(DlaMap::with_settings(width, height, s.settings.clone()))
+        }
+    }
+}
+
+/// Smallest width and height the named generator builds a proper map at, or None if the
+/// name is unknown
+#[pyfunction]
+fn min_size(generator: &str) -> Option<i32> {
+    MapSelector::min_size(generator)
+}
+
+/// Builds one map. A random seed is picked if none is given; it's kept on the map.
+#[pyfunction]
+#[pyo3(signature = (generator, width, height, seed = None))]
+fn generate(py: Python<'_>, generator: Generator, width: i32, height: i32, seed: Option<u64>) -> PyResult<PyMap> {
+    generator.validate(width, height)?;
+    let seed = seed.unwrap_or_else(|| RandomNumberGenerator::new().next_u64());
+
+    let map = py.allow_threads(|| {
+        let mut architect = generator.architect(width, height);
+        architect.build_with_seed(seed);
+        architect.get_map().clone()
+    });
+    PyMap::new(py, map, seed)
+}
+
+/// Builds one map per seed, in parallel when the module is built with `rayon`
+#[pyfunction]
+fn generate_batch(py: Python<'_>, generator: Generator, width: i32, height: i32, seeds: Vec<u64>) -> PyResult<Vec<PyMap>> {
+    generator.validate(width, height)?;
+
+    let maps = py.allow_threads(|| build_batch(|| generator.architect(width, height), &seeds));
+    maps.into_iter()
+        .zip(seeds)
+        .map(|(map, seed)| PyMap::new(py, map, seed))
+        .collect()
+}
+
+#[pymodule]
+fn cartographer(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("GENERATORS", MapSelector::names().collect::<Vec<_>>())?;
+    m.add("MIN_SIZE", MapSelector::MIN_SIZE)?;
+    m.add("WALL", tile_code(TileType::Wall))?;
+    m.add("FLOOR", tile_code(TileType::Floor))?;
+    m.add("EXIT", tile_code(TileType::Exit))?;
+    m.add("VOID", tile_code(TileType::Void))?;
+
+    m.add_class::<PyMap>()?;
+    m.add_class::<Tiles>()?;
+    m.add_class::<PyDrunkardSettings>()?;
+    m.add_class::<PyDlaSettings>()?;
+    m.add_function(wrap_pyfunction!(min_size, m)?)?;
+    m.add_function(wrap_pyfunction!(generate, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_batch, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,129 @@
+"""Smoke tests for the Python bindings.
+
+Build the module into the current environment and run them with:
+
+    maturin develop --features python
+    python -m unittest tests/test_python.py
+"""
+
+import threading
+import unittest
+
+import cartographer
+
+
+class TilesBufferTest(unittest.TestCase):
+    def test_memoryview_is_a_read_only_2d_byte_array(self):
+        m = cartographer.generate("basic_map", 40, 30, seed=1)
+        view = memoryview(m.tiles)
+
+        self.assertEqual(view.shape, (30, 40))
+        self.assertEqual(view.strides, (40, 1))
+        self.assertEqual(view.format, "B")
+        self.assertEqual(view.itemsize, 1)
+        self.assertEqual(view.ndim, 2)
+        self.assertTrue(view.readonly)
+        self.assertEqual(len(m.tiles), 40 * 30)
+
+        with self.assertRaises(TypeError):
+            view[0, 0] = cartographer.FLOOR
+
+    def test_buffer_matches_the_map(self):
+        m = cartographer.generate("cellular_automata_map", 40, 30, seed=2)
+        view = memoryview(m.tiles)
+        rows = str(m).splitlines()
+        codes = {"#": cartographer.WALL, ".": cartographer.FLOOR, "E": cartographer.EXIT, " ": cartographer.VOID}
+
+        for y, row in enumerate(rows):
+            self.assertEqual([view[y, x] for x in range(m.width)], [codes[c] for c in row])
+
+        sx, sy = m.start
+        self.assertEqual(view[sy, sx], cartographer.FLOOR)
+        ex, ey = m.exit
+        self.assertEqual(view[ey, ex], cartographer.EXIT)
+
+    def test_view_outlives_the_map(self):
+        view = memoryview(cartographer.generate("maze_map", 20, 20, seed=3).tiles)
+        self.assertEqual(view.tobytes().count(bytes([cartographer.EXIT])), 1)
+
+
+class GenerateTest(unittest.TestCase):
+    def test_seeds_give_the_same_map(self):
+        a = cartographer.generate("bsp_map", 40, 30, seed=7)
+        b = cartographer.generate("bsp_map", 40, 30, seed=7)
+        self.assertEqual(bytes(a.tiles), bytes(b.tiles))
+        self.assertEqual(a.seed, 7)
+
+    def test_batch_matches_single_maps(self):
+        seeds = [1, 2, 3, 4]
+        maps = cartographer.generate_batch("drunkard_walk_map_open_area", 30, 30, seeds)
+        self.assertEqual([m.seed for m in maps], seeds)
+        for m in maps:
+            single = cartographer.generate("drunkard_walk_map_open_area", 30, 30, seed=m.seed)
+            self.assertEqual(bytes(m.tiles), bytes(single.tiles))
+
+    def test_settings_objects(self):
+        settings = cartographer.DrunkardSettings(spawn_mode="random", symmetry="radial:3", brush_size=2)
+        self.assertEqual(settings.symmetry, "radial:3")
+        m = cartographer.generate(settings, 40, 40, seed=5)
+        self.assertEqual((m.width, m.height), (40, 40))
+
+        m = cartographer.generate(cartographer.DlaSettings.coral(), 40, 40, seed=5)
+        self.assertGreater(m.stats()["floor_ratio"], 0)
+
+    def test_generate_releases_the_gil(self):
+        # Other Python threads keep running while maps are built
+        ticks = []
+        done = threading.Event()
+
+        def tick():
+            while not done.is_set():
+                ticks.append(1)
+                done.wait(0.001)
+
+        ticker = threading.Thread(target=tick)
+        ticker.start()
+        try:
+            cartographer.generate_batch("cellular_automata_map", 150, 150, list(range(8)))
+            during_batch = len(ticks)
+            cartographer.generate("cellular_automata_map", 300, 300, seed=1)
+        finally:
+            done.set()
+            ticker.join()
+        self.assertGreater(during_batch, 10)
+        self.assertGreater(len(ticks) - during_batch, 10)
+
+
+class ValidationTest(unittest.TestCase):
+    def test_unknown_generator(self):
+        with self.assertRaises(ValueError):
+            cartographer.generate("no_such_map", 40, 40)
+        with self.assertRaises(ValueError):
+            cartographer.generate_batch("no_such_map", 40, 40, [1])
+        self.assertIsNone(cartographer.min_size("no_such_map"))
+
+    def test_too_small(self):
+        size = cartographer.min_size("cellular_automata_map")
+        with self.assertRaises(ValueError):
+            cartographer.generate("cellular_automata_map", size - 1, 40)
+        with self.assertRaises(ValueError):
+            cartographer.generate_batch(cartographer.DlaSettings(), 40, 0, [1])
+        cartographer.generate("cellular_automata_map", size, size, seed=1)
+
+    def test_bad_settings(self):
+        with self.assertRaises(ValueError):
+            cartographer.DrunkardSettings(spawn_mode="sideways")
+        with self.assertRaises(ValueError):
+            cartographer.DlaSettings(algorithm="sideways")
+        for symmetry in ["diagonal", "radial:0", "radial:x"]:
+            with self.assertRaises(ValueError):
+                cartographer.DlaSettings(symmetry=symmetry)
+
+        settings = cartographer.DlaSettings()
+        with self.assertRaises(ValueError):
+            settings.symmetry = "diagonal"
+        self.assertEqual(settings.symmetry, "none")
+
+
+if __name__ == "__main__":
+    unittest.main()